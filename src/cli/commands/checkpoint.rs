@@ -0,0 +1,73 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Checkpoint subsystem status cli command
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{CheckpointStatusParams, CheckpointStatusResponse};
+
+/// The command to show the status of the checkpoint subsystem.
+pub(crate) struct CheckpointStatus;
+
+#[async_trait]
+impl CommandLineHandler for CheckpointStatus {
+    type Arguments = CheckpointStatusArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("checkpoint status with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client =
+            get_ipc_agent_client(url, global, json_rpc_methods::CHECKPOINT_STATUS)?;
+
+        let params = CheckpointStatusParams {
+            subnet: arguments.subnet.clone(),
+        };
+
+        let response = json_rpc_client
+            .request::<CheckpointStatusResponse>(
+                json_rpc_methods::CHECKPOINT_STATUS,
+                serde_json::to_value(params)?,
+            )
+            .await?;
+
+        for s in response.subnets {
+            log::info!(
+                "subnet: {:}, parent: {:}, check period: {:?}, last seen epoch: {:?}, last processed epoch: {:?}, restarts: {:}, last error: {:?}",
+                s.child,
+                s.parent,
+                s.check_period,
+                s.last_seen_epoch,
+                s.last_processed_epoch,
+                s.restarts,
+                s.last_error
+            );
+            for a in s.accounts {
+                log::info!(
+                    "    account: {:}, in validator set: {:}, last submitted epoch: {:?}, last submitted cid: {:?}",
+                    a.address,
+                    a.in_validator_set,
+                    a.last_submitted_epoch,
+                    a.last_submitted_cid
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the status of the checkpoint subsystem")]
+pub(crate) struct CheckpointStatusArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "Only show the status of this child subnet")]
+    pub subnet: Option<String>,
+}
@@ -2,13 +2,23 @@
 // SPDX-License-Identifier: MIT
 //! The Daemon command line handler that prints the info about IPC Agent.
 
+use anyhow::anyhow;
 use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_graceful_shutdown::{SubsystemHandle, Toplevel};
 
 use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::ReloadableConfig;
+use crate::manager::{CheckpointSubsystem, CheckpointTracker, CHECKPOINT_STATE_FILE};
 use crate::server::jsonrpc::JsonRPCServer;
 
+/// The maximum time to wait for the subsystems to stop once a shutdown is requested.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// The command to start the ipc agent json rpc server in the foreground.
 pub(crate) struct LaunchDaemon;
 
@@ -23,10 +33,31 @@ impl CommandLineHandler for LaunchDaemon {
             global
         );
 
-        let server = JsonRPCServer::from_config_path(&global.config_path())?;
-        server.run().await?;
+        // The json rpc server and the checkpoint subsystem share the same config, so a
+        // config reload triggered through the json rpc server also reaches the checkpoint
+        // subsystem. They also share the checkpoint tracker, so that the json rpc server can
+        // report the status of the checkpoint subsystem. The checkpointing progress is persisted
+        // next to the config, so that it is resumed after a restart.
+        let config_path = global.config_path();
+        let config = Arc::new(ReloadableConfig::new(config_path.clone())?);
+        let state_path = Path::new(&config_path).with_file_name(CHECKPOINT_STATE_FILE);
+        let tracker = Arc::new(CheckpointTracker::new(state_path)?);
+
+        let server = JsonRPCServer::new(config.clone(), config_path, tracker.clone());
+        let checkpointing = CheckpointSubsystem::new(config, tracker);
 
-        Ok(())
+        Toplevel::new()
+            .start("JSON-RPC server", move |s: SubsystemHandle| async move {
+                server.run(s).await
+            })
+            .start(
+                "Checkpoint subsystem",
+                move |s: SubsystemHandle| async move { checkpointing.run(s).await },
+            )
+            .catch_signals()
+            .handle_shutdown_requests(SHUTDOWN_TIMEOUT)
+            .await
+            .map_err(|e| anyhow!("daemon stopped with error: {e:?}"))
     }
 }
 
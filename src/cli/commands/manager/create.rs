@@ -7,10 +7,11 @@ use clap::Args;
 use fvm_shared::clock::ChainEpoch;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::server::{CreateSubnetParams, CreateSubnetResponse};
 
 /// The command to create a new subnet actor.
@@ -24,7 +25,7 @@ impl CommandLineHandler for CreateSubnet {
         log::debug!("create subnet with args: {:?}", arguments);
 
         let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::CREATE_SUBNET)?;
 
         let params = CreateSubnetParams {
             parent: arguments.parent.clone(),
@@ -33,17 +34,23 @@ impl CommandLineHandler for CreateSubnet {
             min_validators: arguments.min_validators,
             finality_threshold: arguments.finality_threshold,
             check_period: arguments.check_period,
+            tx: arguments.tx.options(),
         };
 
-        let address = json_rpc_client
+        let response = json_rpc_client
             .request::<CreateSubnetResponse>(
                 json_rpc_methods::CREATE_SUBNET,
                 serde_json::to_value(params)?,
             )
-            .await?
-            .address;
+            .await?;
 
-        log::info!("created subnet actor with address: {address:}");
+        match response.address {
+            Some(address) => log::info!(
+                "created subnet actor with address: {address:}, {:}",
+                response.receipt
+            ),
+            None => log::info!("submitted subnet actor creation, {:}", response.receipt),
+        }
 
         Ok(())
     }
@@ -66,4 +73,6 @@ pub(crate) struct CreateSubnetArgs {
     pub finality_threshold: ChainEpoch,
     #[arg(help = "The checkpoint period")]
     pub check_period: ChainEpoch,
+    #[command(flatten)]
+    pub tx: TxArgs,
 }
@@ -0,0 +1,61 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Fund subnet cli command handler.
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{FundSubnetParams, TxResponse};
+
+/// The command to send funds to a subnet from the parent
+pub(crate) struct FundSubnet;
+
+#[async_trait]
+impl CommandLineHandler for FundSubnet {
+    type Arguments = FundSubnetArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("fund subnet with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::FUND_SUBNET)?;
+
+        // The json rpc server will handle directing the request to
+        // the correct parent.
+        let params = FundSubnetParams {
+            subnet: arguments.subnet.clone(),
+            from: arguments.from.clone(),
+            amount: arguments.amount,
+            tx: arguments.tx.options(),
+        };
+
+        let receipt = json_rpc_client
+            .request::<TxResponse>(json_rpc_methods::FUND_SUBNET, serde_json::to_value(params)?)
+            .await?;
+
+        log::info!("funded subnet: {:}, {receipt:}", arguments.subnet);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Send funds from a parent to a child subnet")]
+pub(crate) struct FundSubnetArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "The address to send funds from")]
+    pub from: Option<String>,
+    #[arg(help = "The subnet to fund")]
+    pub subnet: String,
+    #[arg(help = "The amount to fund in attoFIL")]
+    pub amount: u64,
+    #[command(flatten)]
+    pub tx: TxArgs,
+}
@@ -6,11 +6,12 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
-use crate::server::JoinSubnetParams;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{JoinSubnetParams, TxResponse};
 
 /// The command to join a subnet
 pub(crate) struct JoinSubnet;
@@ -23,7 +24,7 @@ impl CommandLineHandler for JoinSubnet {
         log::debug!("join subnet with args: {:?}", arguments);
 
         let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::JOIN_SUBNET)?;
 
         // The json rpc server will handle directing the request to
         // the correct parent.
@@ -32,13 +33,14 @@ impl CommandLineHandler for JoinSubnet {
             from: arguments.from.clone(),
             collateral: arguments.collateral,
             validator_net_addr: arguments.validator_net_addr.clone(),
+            tx: arguments.tx.options(),
         };
 
-        json_rpc_client
-            .request::<()>(json_rpc_methods::JOIN_SUBNET, serde_json::to_value(params)?)
+        let receipt = json_rpc_client
+            .request::<TxResponse>(json_rpc_methods::JOIN_SUBNET, serde_json::to_value(params)?)
             .await?;
 
-        log::info!("joined subnet: {:}", arguments.subnet);
+        log::info!("joined subnet: {:}, {receipt:}", arguments.subnet);
 
         Ok(())
     }
@@ -57,4 +59,6 @@ pub(crate) struct JoinSubnetArgs {
     pub collateral: u64,
     #[arg(help = "The validator net address")]
     pub validator_net_addr: String,
+    #[command(flatten)]
+    pub tx: TxArgs,
 }
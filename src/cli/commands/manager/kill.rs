@@ -6,11 +6,12 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
-use crate::server::KillSubnetParams;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{KillSubnetParams, TxResponse};
 
 /// The command to kill an existing subnet.
 pub(crate) struct KillSubnet;
@@ -23,18 +24,19 @@ impl CommandLineHandler for KillSubnet {
         log::debug!("kill subnet with args: {:?}", arguments);
 
         let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::KILL_SUBNET)?;
 
         let params = KillSubnetParams {
             subnet: arguments.subnet.clone(),
             from: arguments.from.clone(),
+            tx: arguments.tx.options(),
         };
 
-        json_rpc_client
-            .request::<()>(json_rpc_methods::KILL_SUBNET, serde_json::to_value(params)?)
+        let receipt = json_rpc_client
+            .request::<TxResponse>(json_rpc_methods::KILL_SUBNET, serde_json::to_value(params)?)
             .await?;
 
-        log::info!("killed subnet: {:}", arguments.subnet);
+        log::info!("killed subnet: {:}, {receipt:}", arguments.subnet);
 
         Ok(())
     }
@@ -49,4 +51,6 @@ pub(crate) struct KillSubnetArgs {
     pub from: Option<String>,
     #[arg(help = "The subnet to kill")]
     pub subnet: String,
+    #[command(flatten)]
+    pub tx: TxArgs,
 }
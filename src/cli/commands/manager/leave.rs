@@ -6,11 +6,12 @@ use async_trait::async_trait;
 use clap::Args;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
-use crate::server::LeaveSubnetParams;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{LeaveSubnetParams, TxResponse};
 
 /// The command to leave a new subnet.
 pub(crate) struct LeaveSubnet;
@@ -23,21 +24,22 @@ impl CommandLineHandler for LeaveSubnet {
         log::debug!("leave subnet with args: {:?}", arguments);
 
         let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::LEAVE_SUBNET)?;
 
         let params = LeaveSubnetParams {
             subnet: arguments.subnet.clone(),
             from: arguments.from.clone(),
+            tx: arguments.tx.options(),
         };
 
-        json_rpc_client
-            .request::<()>(
+        let receipt = json_rpc_client
+            .request::<TxResponse>(
                 json_rpc_methods::LEAVE_SUBNET,
                 serde_json::to_value(params)?,
             )
             .await?;
 
-        log::info!("left subnet: {:}", arguments.subnet);
+        log::info!("left subnet: {:}, {receipt:}", arguments.subnet);
 
         Ok(())
     }
@@ -52,4 +54,6 @@ pub(crate) struct LeaveSubnetArgs {
     pub from: Option<String>,
     #[arg(help = "The subnet to leave")]
     pub subnet: String,
+    #[command(flatten)]
+    pub tx: TxArgs,
 }
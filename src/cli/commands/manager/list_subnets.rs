@@ -8,10 +8,10 @@ use ipc_sdk::subnet_id::SubnetID;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
-use crate::cli::commands::get_ipc_agent_url;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
 use crate::cli::{CommandLineHandler, GlobalArguments};
 use crate::config::json_rpc_methods;
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::JsonRpcClient;
 use crate::manager::SubnetInfo;
 use crate::server::ListSubnetsParams;
 
@@ -26,7 +26,8 @@ impl CommandLineHandler for ListSubnets {
         log::debug!("list subnets with args: {:?}", arguments);
 
         let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
-        let json_rpc_client = JsonRpcClientImpl::new(url, None);
+        let json_rpc_client =
+            get_ipc_agent_client(url, global, json_rpc_methods::LIST_CHILD_SUBNETS)?;
 
         let params = ListSubnetsParams {
             gateway_address: arguments.gateway_address.clone(),
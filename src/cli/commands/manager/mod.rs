@@ -1,7 +1,33 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 pub mod create;
+pub mod fund;
 pub mod join;
 pub mod kill;
 pub mod leave;
 pub mod list_subnets;
+pub mod propagate;
+pub mod release;
+pub mod whitelist;
+
+use clap::Args;
+
+use crate::server::TxOptions;
+
+/// The arguments of the commands sending a message, which wait for its execution by default.
+#[derive(Debug, Args)]
+pub(crate) struct TxArgs {
+    #[arg(
+        long,
+        help = "Return the message cid as soon as it is submitted, without waiting for its execution"
+    )]
+    pub no_wait: bool,
+}
+
+impl TxArgs {
+    pub fn options(&self) -> TxOptions {
+        TxOptions {
+            wait: !self.no_wait,
+        }
+    }
+}
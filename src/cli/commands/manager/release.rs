@@ -0,0 +1,65 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Release funds cli command handler.
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{ReleaseFundsParams, TxResponse};
+
+/// The command to release funds from a subnet back to its parent
+pub(crate) struct ReleaseFunds;
+
+#[async_trait]
+impl CommandLineHandler for ReleaseFunds {
+    type Arguments = ReleaseFundsArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("release funds with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::RELEASE_FUNDS)?;
+
+        let params = ReleaseFundsParams {
+            subnet: arguments.subnet.clone(),
+            from: arguments.from.clone(),
+            amount: arguments.amount,
+            tx: arguments.tx.options(),
+        };
+
+        let receipt = json_rpc_client
+            .request::<TxResponse>(
+                json_rpc_methods::RELEASE_FUNDS,
+                serde_json::to_value(params)?,
+            )
+            .await?;
+
+        log::info!(
+            "released funds from subnet: {:}, {receipt:}",
+            arguments.subnet
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Release funds from a child subnet to its parent")]
+pub(crate) struct ReleaseFundsArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "The address to release funds from")]
+    pub from: Option<String>,
+    #[arg(help = "The subnet to release funds from")]
+    pub subnet: String,
+    #[arg(help = "The amount to release in attoFIL")]
+    pub amount: u64,
+    #[command(flatten)]
+    pub tx: TxArgs,
+}
@@ -0,0 +1,70 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Whitelist propagator cli command handler.
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::manager::TxArgs;
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{TxResponse, WhitelistPropagatorParams};
+
+/// The command to whitelist propagators of a message in the postbox.
+pub(crate) struct WhitelistPropagator;
+
+#[async_trait]
+impl CommandLineHandler for WhitelistPropagator {
+    type Arguments = WhitelistPropagatorArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("whitelist propagator with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client =
+            get_ipc_agent_client(url, global, json_rpc_methods::WHITELIST_PROPAGATOR)?;
+
+        let params = WhitelistPropagatorParams {
+            subnet: arguments.subnet.clone(),
+            from: arguments.from.clone(),
+            postbox_msg_cid: arguments.postbox_msg_cid.clone(),
+            to_add: arguments.to_add.clone(),
+            tx: arguments.tx.options(),
+        };
+
+        let receipt = json_rpc_client
+            .request::<TxResponse>(
+                json_rpc_methods::WHITELIST_PROPAGATOR,
+                serde_json::to_value(params)?,
+            )
+            .await?;
+
+        log::info!(
+            "whitelisted propagators: {:?} for postbox message: {:}, {receipt:}",
+            arguments.to_add,
+            arguments.postbox_msg_cid
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Whitelist propagators of a cross-net message in the postbox")]
+pub(crate) struct WhitelistPropagatorArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(long, short, help = "The owner address of the postbox message")]
+    pub from: Option<String>,
+    #[arg(help = "The subnet whose gateway holds the postbox message")]
+    pub subnet: String,
+    #[arg(help = "The cid of the postbox message")]
+    pub postbox_msg_cid: String,
+    #[arg(required = true, help = "The addresses to whitelist as propagators")]
+    pub to_add: Vec<String>,
+    #[command(flatten)]
+    pub tx: TxArgs,
+}
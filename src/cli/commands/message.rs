@@ -0,0 +1,64 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Message status cli command
+
+use async_trait::async_trait;
+use clap::Args;
+use std::fmt::Debug;
+
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::JsonRpcClient;
+use crate::server::{MessageStatusParams, MessageStatusResponse};
+
+/// The command to show the status of the messages submitted by the agent.
+pub(crate) struct MessageStatus;
+
+#[async_trait]
+impl CommandLineHandler for MessageStatus {
+    type Arguments = MessageStatusArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("message status with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::MESSAGE_STATUS)?;
+
+        let params = MessageStatusParams {
+            message_cid: arguments.message_cid.clone(),
+        };
+
+        let response = json_rpc_client
+            .request::<MessageStatusResponse>(
+                json_rpc_methods::MESSAGE_STATUS,
+                serde_json::to_value(params)?,
+            )
+            .await?;
+
+        for m in response.messages {
+            match (m.receipt, m.error) {
+                (Some(receipt), _) => log::info!("{:?}, {receipt:}", m.state),
+                (None, Some(error)) => {
+                    log::info!("message cid: {:}, {:?}: {error:}", m.message_cid, m.state)
+                }
+                (None, None) => log::info!("message cid: {:}, {:?}", m.message_cid, m.state),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Show the status of the messages submitted by the agent")]
+pub(crate) struct MessageStatusArgs {
+    #[arg(long, short, help = "The JSON RPC server url for ipc agent")]
+    pub ipc_agent_url: Option<String>,
+    #[arg(
+        long,
+        short,
+        help = "Only show the status of the message with this cid"
+    )]
+    pub message_cid: Option<String>,
+}
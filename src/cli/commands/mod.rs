@@ -2,17 +2,30 @@
 // SPDX-License-Identifier: MIT
 //! This mod contains the different command line implementations.
 
+mod checkpoint;
 mod config;
 mod daemon;
 mod manager;
+mod message;
+mod openrpc;
 
+use crate::cli::commands::checkpoint::{CheckpointStatus, CheckpointStatusArgs};
 use crate::cli::commands::config::{ReloadConfig, ReloadConfigArgs};
 use crate::cli::commands::daemon::{LaunchDaemon, LaunchDaemonArgs};
+use crate::cli::commands::manager::fund::{FundSubnet, FundSubnetArgs};
 use crate::cli::commands::manager::join::{JoinSubnet, JoinSubnetArgs};
 use crate::cli::commands::manager::kill::{KillSubnet, KillSubnetArgs};
 use crate::cli::commands::manager::leave::{LeaveSubnet, LeaveSubnetArgs};
+use crate::cli::commands::manager::propagate::{Propagate, PropagateArgs};
+use crate::cli::commands::manager::release::{ReleaseFunds, ReleaseFundsArgs};
+use crate::cli::commands::manager::whitelist::{WhitelistPropagator, WhitelistPropagatorArgs};
+use crate::cli::commands::message::{MessageStatus, MessageStatusArgs};
+use crate::cli::commands::openrpc::{OpenRpc, OpenRpcArgs};
 use crate::cli::{CommandLineHandler, GlobalArguments};
-use anyhow::Result;
+use crate::jsonrpc::JsonRpcClientImpl;
+use crate::manager::MessageExecutionError;
+use crate::server::required_permission;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use manager::create::{CreateSubnet, CreateSubnetArgs};
 use manager::list_subnets::{ListSubnets, ListSubnetsArgs};
@@ -35,6 +48,14 @@ enum Commands {
     JoinSubnet(JoinSubnetArgs),
     LeaveSubnet(LeaveSubnetArgs),
     KillSubnet(KillSubnetArgs),
+    FundSubnet(FundSubnetArgs),
+    ReleaseFunds(ReleaseFundsArgs),
+    Propagate(PropagateArgs),
+    WhitelistPropagator(WhitelistPropagatorArgs),
+    CheckpointStatus(CheckpointStatusArgs),
+    MessageStatus(MessageStatusArgs),
+    #[command(name = "openrpc")]
+    OpenRpc(OpenRpcArgs),
 }
 
 /// The overall command line struct to be used by `clap`.
@@ -93,14 +114,25 @@ pub async fn cli() {
         Commands::JoinSubnet(args) => JoinSubnet::handle(global, args).await,
         Commands::LeaveSubnet(args) => LeaveSubnet::handle(global, args).await,
         Commands::KillSubnet(args) => KillSubnet::handle(global, args).await,
+        Commands::FundSubnet(args) => FundSubnet::handle(global, args).await,
+        Commands::ReleaseFunds(args) => ReleaseFunds::handle(global, args).await,
+        Commands::Propagate(args) => Propagate::handle(global, args).await,
+        Commands::WhitelistPropagator(args) => WhitelistPropagator::handle(global, args).await,
+        Commands::CheckpointStatus(args) => CheckpointStatus::handle(global, args).await,
+        Commands::MessageStatus(args) => MessageStatus::handle(global, args).await,
+        Commands::OpenRpc(args) => OpenRpc::handle(global, args).await,
     };
 
     if let Err(e) = r {
-        log::error!(
-            "process command: {:?} failed due to error: {:?}",
-            args.command,
-            e
-        )
+        // Failed message executions are reported by the agent with a structured error.
+        match MessageExecutionError::from_rpc_error(&e) {
+            Some(err) => log::error!("process command: {:?} failed: {err}", args.command),
+            None => log::error!(
+                "process command: {:?} failed due to error: {:?}",
+                args.command,
+                e
+            ),
+        }
     }
 }
 
@@ -113,11 +145,51 @@ pub(crate) fn get_ipc_agent_url(
         None => {
             let config = global.config()?;
             let addr = config.server.json_rpc_address.to_string();
-            // We are resolving back to our own ipc-agent node.
-            // Since it's our own node, we will use http since we
-            // should be in the same network.
-            format!("http://{addr:}/json_rpc").parse()?
+            // We are resolving back to our own ipc-agent node, which is served over https only
+            // when it is configured with a tls certificate.
+            let scheme = match config.server.tls()? {
+                Some(_) => "https",
+                None => "http",
+            };
+            format!("{scheme:}://{addr:}/json_rpc").parse()?
         }
     };
     Ok(url)
 }
+
+/// Returns the client sending the requests for `method` to the ipc agent at `url`, authenticated
+/// with a token of the config and trusting the custom CA of the global arguments, if any.
+pub(crate) fn get_ipc_agent_client(
+    url: Url,
+    global: &GlobalArguments,
+    method: &str,
+) -> Result<JsonRpcClientImpl> {
+    let token = get_ipc_agent_token(global, method);
+    let client = JsonRpcClientImpl::new(url, token.as_deref());
+    match global.tls_ca_path() {
+        Some(path) => {
+            let pem = std::fs::read(path).with_context(|| format!("cannot read CA {path:}"))?;
+            client.with_root_certificate(&pem)
+        }
+        None => Ok(client),
+    }
+}
+
+/// Returns the bearer token to call `method` of the ipc agent with, if the config requires the
+/// requests to be authenticated. It is the least privileged token allowed to call `method`.
+fn get_ipc_agent_token(global: &GlobalArguments, method: &str) -> Option<String> {
+    // The config may be missing when the url of the agent is passed explicitly.
+    let config = match global.config() {
+        Ok(config) => config,
+        Err(e) => {
+            log::debug!("cannot read the config for the agent token: {e:?}");
+            return None;
+        }
+    };
+    config
+        .server
+        .auth
+        .as_ref()
+        .and_then(|auth| auth.client_token(required_permission(method)))
+        .map(String::from)
+}
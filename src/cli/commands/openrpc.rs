@@ -0,0 +1,57 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Export the OpenRPC document of the IPC-Agent Json RPC server
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::Value;
+use std::fmt::Debug;
+
+use crate::cli::commands::{get_ipc_agent_client, get_ipc_agent_url};
+use crate::cli::{CommandLineHandler, GlobalArguments};
+use crate::config::json_rpc_methods;
+use crate::jsonrpc::{JsonRpcClient, NO_PARAMS};
+
+/// The command to export the OpenRPC document of the agent.
+pub(crate) struct OpenRpc;
+
+#[async_trait]
+impl CommandLineHandler for OpenRpc {
+    type Arguments = OpenRpcArgs;
+
+    async fn handle(global: &GlobalArguments, arguments: &Self::Arguments) -> anyhow::Result<()> {
+        log::debug!("openrpc with args: {:?}", arguments);
+
+        let url = get_ipc_agent_url(&arguments.ipc_agent_url, global)?;
+        let json_rpc_client = get_ipc_agent_client(url, global, json_rpc_methods::RPC_DISCOVER)?;
+
+        let document = json_rpc_client
+            .request::<Value>(json_rpc_methods::RPC_DISCOVER, NO_PARAMS)
+            .await?;
+        let document = serde_json::to_string_pretty(&document)?;
+
+        // The document is printed as is, so that it can be piped to other tools.
+        match &arguments.output {
+            Some(path) => {
+                std::fs::write(path, document)?;
+                log::info!("OpenRPC document written to {path:}");
+            }
+            None => println!("{document}"),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(about = "Export the OpenRPC document of the IPC Agent JSON RPC server")]
+pub(crate) struct OpenRpcArgs {
+    #[arg(
+        short,
+        long,
+        help = "The file to write the document to, printed to the standard output if missing"
+    )]
+    pub output: Option<String>,
+    #[arg(short, long, help = "The JSON RPC server url for ipc agent, optional")]
+    pub ipc_agent_url: Option<String>,
+}
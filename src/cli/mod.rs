@@ -36,6 +36,12 @@ pub struct GlobalArguments {
         help = "The toml config file path for IPC Agent, default to ${HOME}/.ipc_agent/config.toml"
     )]
     config_path: Option<String>,
+    #[arg(
+        long,
+        env = "IPC_AGENT_TLS_CA_PATH",
+        help = "The PEM certificate of a custom CA to trust when connecting to IPC Agent over https"
+    )]
+    tls_ca_path: Option<String>,
 }
 
 impl GlobalArguments {
@@ -49,6 +55,10 @@ impl GlobalArguments {
         })
     }
 
+    pub fn tls_ca_path(&self) -> Option<&str> {
+        self.tls_ca_path.as_deref()
+    }
+
     pub fn config(&self) -> Result<Config> {
         let config_path = self.config_path();
         Config::from_file(config_path)
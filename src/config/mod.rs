@@ -21,7 +21,7 @@ use anyhow::Result;
 pub use reload::ReloadableConfig;
 use serde::Deserialize;
 pub use server::JSON_RPC_ENDPOINT;
-pub use server::{json_rpc_methods, Server};
+pub use server::{json_rpc_methods, Permission, Server, ServerAuth};
 pub use subnet::Subnet;
 
 pub const JSON_RPC_VERSION: &str = "2.0";
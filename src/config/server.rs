@@ -1,13 +1,93 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
+use anyhow::{anyhow, Result};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 pub const JSON_RPC_ENDPOINT: &str = "json_rpc";
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Server {
     pub json_rpc_address: SocketAddr,
+    /// The authentication of the requests. Every request is allowed if missing.
+    pub auth: Option<ServerAuth>,
+    /// The PEM certificate chain and private key the requests are served over TLS with. The
+    /// requests are served over plain http if both are missing.
+    pub tls_cert_path: Option<PathBuf>,
+    pub tls_key_path: Option<PathBuf>,
+}
+
+impl Server {
+    /// Returns the paths of the certificate chain and private key if TLS is enabled.
+    pub fn tls(&self) -> Result<Option<(&Path, &Path)>> {
+        match (&self.tls_cert_path, &self.tls_key_path) {
+            (Some(cert), Some(key)) => Ok(Some((cert, key))),
+            (None, None) => Ok(None),
+            _ => Err(anyhow!(
+                "tls_cert_path and tls_key_path must be set together"
+            )),
+        }
+    }
+}
+
+/// The bearer tokens accepted by the json rpc server, with the permission each of them grants.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ServerAuth {
+    pub tokens: HashMap<String, Permission>,
+}
+
+impl ServerAuth {
+    /// Returns the permission granted to `token`, if it is accepted.
+    pub fn permission(&self, token: &str) -> Option<Permission> {
+        self.tokens.get(token).copied()
+    }
+
+    /// Returns the token sent by the cli to call a method requiring the `required` permission,
+    /// which is the least privileged token granting it. If no token grants it, the most
+    /// privileged one is returned, so that the agent reports the missing permission.
+    pub fn client_token(&self, required: Permission) -> Option<&str> {
+        let least_privileged = self
+            .tokens
+            .iter()
+            .filter(|(_, p)| **p >= required)
+            .min_by(|(a, p), (b, q)| p.cmp(q).then_with(|| a.cmp(b)));
+        least_privileged
+            .or_else(|| {
+                self.tokens
+                    .iter()
+                    .max_by(|(a, p), (b, q)| p.cmp(q).then_with(|| b.cmp(a)))
+            })
+            .map(|(token, _)| token.as_str())
+    }
+}
+
+/// The permission levels of the json rpc methods, following the ones of Lotus. Each level
+/// includes the permissions of the levels below it.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Querying the state of the agent and of the subnets.
+    Read,
+    /// Changing the state of the agent without sending messages, e.g. subscribing to its events.
+    Write,
+    /// Sending messages signed with the keys of the wallet.
+    Sign,
+    /// Administering the agent, e.g. reloading its config.
+    Admin,
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Sign => "sign",
+            Permission::Admin => "admin",
+        };
+        write!(f, "{s}")
+    }
 }
 
 pub mod json_rpc_methods {
@@ -15,7 +95,20 @@ pub mod json_rpc_methods {
     pub const JOIN_SUBNET: &str = "ipc_joinSubnet";
     pub const LEAVE_SUBNET: &str = "ipc_leaveSubnet";
     pub const KILL_SUBNET: &str = "ipc_killSubnet";
+    pub const FUND_SUBNET: &str = "ipc_fundSubnet";
+    pub const RELEASE_FUNDS: &str = "ipc_releaseFunds";
+    pub const PROPAGATE: &str = "ipc_propagate";
+    pub const WHITELIST_PROPAGATOR: &str = "ipc_whitelistPropagator";
     pub const LIST_CHILD_SUBNETS: &str = "ipc_listChildSubnets";
     pub const RELOAD_CONFIG: &str = "ipc_reloadConfig";
     pub const QUERY_VALIDATOR_SET: &str = "ipc_queryValidatorSet";
+    pub const CHECKPOINT_STATUS: &str = "ipc_checkpointStatus";
+    pub const MESSAGE_STATUS: &str = "ipc_messageStatus";
+    pub const SUBSCRIBE_CHECKPOINTS: &str = "ipc_subscribeCheckpoints";
+    pub const SUBSCRIBE_SUBNET_EVENTS: &str = "ipc_subscribeSubnetEvents";
+    pub const UNSUBSCRIBE: &str = "ipc_unsubscribe";
+    /// The method of the notifications of the subscriptions.
+    pub const SUBSCRIPTION: &str = "ipc_subscription";
+    /// The method returning the OpenRPC document of the server.
+    pub const RPC_DISCOVER: &str = "rpc.discover";
 }
@@ -13,8 +13,25 @@ pub struct Subnet {
     #[serde(deserialize_with = "deserialize_subnet_id")]
     pub id: SubnetID,
     pub jsonrpc_api_http: Url,
+    /// The urls to send requests to when `jsonrpc_api_http` is unhealthy, in order of preference.
+    #[serde(default)]
+    pub jsonrpc_api_http_fallbacks: Vec<Url>,
     pub jsonrpc_api_ws: Option<Url>,
     pub auth_token: Option<String>,
+    /// The timeout of each request, in seconds. It also bounds the requests that wait for a
+    /// message to be executed. If missing, no timeout is set on the HTTP/HTTPS requests and the
+    /// websocket requests use a default timeout.
+    pub request_timeout: Option<u64>,
+    /// The number of times a request is retried after a connection error.
+    #[serde(default)]
+    pub max_retries: u32,
+    /// The time to wait before retrying a request, in seconds. It is doubled after each retry.
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
     #[serde(deserialize_with = "deserialize_accounts", default)]
     pub accounts: Vec<Address>,
 }
+
+fn default_retry_backoff() -> u64 {
+    1
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 use std::io::Write;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::{Arc, Condvar, Mutex};
 
@@ -11,7 +12,7 @@ use ipc_sdk::subnet_id::{SubnetID, ROOTNET_ID};
 use tempfile::NamedTempFile;
 use url::Url;
 
-use crate::config::{Config, ReloadableConfig};
+use crate::config::{Config, Permission, ReloadableConfig};
 
 // Arguments for the config's fields
 const SERVER_JSON_RPC_ADDR: &str = "127.0.0.1:3030";
@@ -19,8 +20,11 @@ const ROOT_ID: &str = "/root";
 const CHILD_ID: &str = "/root/f0100";
 const ROOT_AUTH_TOKEN: &str = "ROOT_AUTH_TOKEN";
 const CHILD_AUTH_TOKEN: &str = "CHILD_AUTH_TOKEN";
+const SERVER_READ_TOKEN: &str = "SERVER_READ_TOKEN";
+const SERVER_ADMIN_TOKEN: &str = "SERVER_ADMIN_TOKEN";
 const JSONRPC_API_HTTP: &str = "https://example.org/rpc/v0";
 const JSONRPC_API_WS: &str = "ws://example.org/rpc/v0";
+const JSONRPC_API_HTTP_FALLBACK: &str = "https://fallback.example.org/rpc/v0";
 const ACCOUNT_ADDRESS: &str =
     "f3thgjtvoi65yzdcoifgqh6utjbaod3ukidxrx34heu34d6avx6z7r5766t5jqt42a44ehzcnw3u5ehz47n42a";
 
@@ -89,6 +93,49 @@ fn check_server_config() {
         SocketAddr::from_str(SERVER_JSON_RPC_ADDR).unwrap(),
         "invalid server rpc address"
     );
+
+    let auth = config.auth.unwrap();
+    assert_eq!(auth.permission(SERVER_READ_TOKEN), Some(Permission::Read));
+    assert_eq!(auth.permission(SERVER_ADMIN_TOKEN), Some(Permission::Admin));
+    assert_eq!(auth.permission(ROOT_AUTH_TOKEN), None);
+    // The cli uses the least privileged token allowed to call a method.
+    assert_eq!(auth.client_token(Permission::Read), Some(SERVER_READ_TOKEN));
+    assert_eq!(
+        auth.client_token(Permission::Write),
+        Some(SERVER_ADMIN_TOKEN)
+    );
+    assert_eq!(
+        auth.client_token(Permission::Admin),
+        Some(SERVER_ADMIN_TOKEN)
+    );
+    assert!(config.tls().unwrap().is_none());
+}
+
+#[test]
+fn check_server_tls_config() {
+    let server_config = |tls: &str| {
+        Config::from_toml_str(&formatdoc!(
+            r#"
+                [server]
+                json_rpc_address = "{SERVER_JSON_RPC_ADDR}"
+                {tls}
+
+                [subnets]
+            "#
+        ))
+        .unwrap()
+        .server
+    };
+
+    let config =
+        server_config("tls_cert_path = \"/etc/ipc/cert.pem\"\ntls_key_path = \"/etc/ipc/key.pem\"");
+    let (cert, key) = config.tls().unwrap().unwrap();
+    assert_eq!(cert, Path::new("/etc/ipc/cert.pem"));
+    assert_eq!(key, Path::new("/etc/ipc/key.pem"));
+
+    // Both paths must be set.
+    let config = server_config("tls_cert_path = \"/etc/ipc/cert.pem\"");
+    assert!(config.tls().is_err());
 }
 
 #[test]
@@ -106,6 +153,13 @@ fn check_subnets_config() {
         &Url::from_str(JSONRPC_API_WS).unwrap()
     );
     assert_eq!(root.auth_token.as_ref().unwrap(), ROOT_AUTH_TOKEN);
+    assert_eq!(
+        root.jsonrpc_api_http_fallbacks,
+        vec![Url::from_str(JSONRPC_API_HTTP_FALLBACK).unwrap()]
+    );
+    assert_eq!(root.request_timeout, Some(30));
+    assert_eq!(root.max_retries, 3);
+    assert_eq!(root.retry_backoff, 2);
 
     let child = &config["child"];
     assert_eq!(child.id, SubnetID::from_str(CHILD_ID).unwrap(),);
@@ -114,6 +168,10 @@ fn check_subnets_config() {
         Url::from_str(JSONRPC_API_HTTP).unwrap(),
     );
     assert_eq!(child.auth_token.as_ref().unwrap(), CHILD_AUTH_TOKEN,);
+    assert!(child.jsonrpc_api_http_fallbacks.is_empty());
+    assert_eq!(child.request_timeout, None);
+    assert_eq!(child.max_retries, 0);
+    assert_eq!(child.retry_backoff, 1);
     assert_eq!(
         child.accounts.as_ref(),
         vec![Address::from_str(ACCOUNT_ADDRESS).unwrap()],
@@ -168,13 +226,21 @@ fn read_config() -> Config {
             [server]
             json_rpc_address = "{SERVER_JSON_RPC_ADDR}"
 
+            [server.auth.tokens]
+            {SERVER_READ_TOKEN} = "read"
+            {SERVER_ADMIN_TOKEN} = "admin"
+
             [subnets]
 
             [subnets.root]
             id = "{ROOT_ID}"
             jsonrpc_api_http = "{JSONRPC_API_HTTP}"
+            jsonrpc_api_http_fallbacks = ["{JSONRPC_API_HTTP_FALLBACK}"]
             jsonrpc_api_ws = "{JSONRPC_API_WS}"
             auth_token = "{ROOT_AUTH_TOKEN}"
+            request_timeout = 30
+            max_retries = 3
+            retry_backoff = 2
 
             [subnets.child]
             id = "{CHILD_ID}"
@@ -0,0 +1,98 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The error returned by a JSON-RPC server
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The error object of a JSON-RPC response. It is returned wrapped in an [`anyhow::Error`], from
+/// which it can be recovered with `downcast_ref::<JsonRpcError>()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, thiserror::Error)]
+#[error("json_rpc error {code}: {message}")]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// The well-known errors returned by Lotus. Lotus reports most of its errors with the same
+/// generic code, so they are recognized by their message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonRpcErrorKind {
+    /// The actor, e.g. the one of an account or a subnet, does not exist in the state tree.
+    ActorNotFound,
+    /// The sender cannot pay for the value and the gas of a message.
+    InsufficientFunds,
+    /// The nonce of a message was already used by another message of the same sender.
+    NonceTooLow,
+    /// Any other error.
+    Other,
+}
+
+impl JsonRpcError {
+    pub fn kind(&self) -> JsonRpcErrorKind {
+        let message = self.message.to_lowercase();
+        if message.contains("actor not found") {
+            JsonRpcErrorKind::ActorNotFound
+        } else if message.contains("not enough funds") || message.contains("insufficient funds") {
+            JsonRpcErrorKind::InsufficientFunds
+        } else if message.contains("nonce too low") {
+            JsonRpcErrorKind::NonceTooLow
+        } else {
+            JsonRpcErrorKind::Other
+        }
+    }
+
+    /// Returns the kind of the `JsonRpcError` wrapped in `error`, if any.
+    pub fn kind_of(error: &anyhow::Error) -> Option<JsonRpcErrorKind> {
+        error.downcast_ref::<JsonRpcError>().map(JsonRpcError::kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::jsonrpc::{JsonRpcError, JsonRpcErrorKind};
+
+    fn error(message: &str) -> JsonRpcError {
+        JsonRpcError {
+            code: 1,
+            message: String::from(message),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(
+            error("resolution lookup failed (t0100): actor not found").kind(),
+            JsonRpcErrorKind::ActorNotFound
+        );
+        assert_eq!(
+            error("mpool push: not enough funds (required: 10, balance: 1)").kind(),
+            JsonRpcErrorKind::InsufficientFunds
+        );
+        assert_eq!(
+            error("message nonce too low").kind(),
+            JsonRpcErrorKind::NonceTooLow
+        );
+        assert_eq!(error("unknown").kind(), JsonRpcErrorKind::Other);
+    }
+
+    #[test]
+    fn test_downcast() {
+        let e = anyhow::Error::from(error("message nonce too low")).context("pushing message");
+        assert_eq!(
+            JsonRpcError::kind_of(&e),
+            Some(JsonRpcErrorKind::NonceTooLow)
+        );
+        assert_eq!(JsonRpcError::kind_of(&anyhow!("other")), None);
+
+        let raw = r#"{"code":-32602,"message":"invalid params","data":{"field":"epoch"}}"#;
+        let e: JsonRpcError = serde_json::from_str(raw).unwrap();
+        assert_eq!(e.code, -32602);
+        assert!(e.data.is_some());
+    }
+}
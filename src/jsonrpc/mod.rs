@@ -3,6 +3,7 @@
 use anyhow::{anyhow, Result};
 use async_channel::{Receiver, Sender};
 use async_trait::async_trait;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use reqwest::header::HeaderValue;
 use reqwest::Client;
@@ -10,19 +11,47 @@ use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::json;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
-use tokio::spawn;
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::sleep;
+use tokio::{select, spawn};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::MaybeTlsStream;
 use tokio_tungstenite::{connect_async, WebSocketStream};
 use url::Url;
 
+mod error;
 #[cfg(test)]
 mod tests;
 
+pub use error::{JsonRpcError, JsonRpcErrorKind};
+
 const DEFAULT_JSON_RPC_VERSION: &str = "2.0";
-const DEFAULT_JSON_RPC_ID: u8 = 1;
+/// The time to wait before subscribing again after a subscription is first interrupted. It is
+/// doubled after each failed attempt, up to `MAX_RECONNECT_BACKOFF`.
+const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+/// How long an endpoint is considered unhealthy by default after it fails to serve a request.
+const DEFAULT_UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+/// The maximum time to wait before subscribing again.
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(60);
+/// The maximum time to wait for a websocket connection to open when no request timeout is set.
+const DEFAULT_WS_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// The maximum time to wait for the responses to a websocket request when no request timeout is
+/// set, so that a response lost by the server does not leave the request pending forever. It is
+/// long enough for the requests waiting for a message to be executed.
+const DEFAULT_WS_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// The method of the notifications through which Lotus sends the values of the channel returned
+/// by a subscription, and the method notifying that the channel was closed.
+const XRPC_CHANNEL_VALUE: &str = "xrpc.ch.val";
+const XRPC_CHANNEL_CLOSE: &str = "xrpc.ch.close";
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 /// A convenience constant that represents empty params in a JSON-RPC request.
 pub const NO_PARAMS: Value = json!([]);
@@ -35,17 +64,76 @@ pub trait JsonRpcClient {
     /// Sends a JSON-RPC request with `method` and `params` via HTTP/HTTPS.
     async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T>;
 
-    /// Subscribes to notifications via a Websocket. This returns a [`Receiver`]
-    /// channel that is used to receive the messages sent by the server.
-    /// TODO: https://github.com/consensus-shipyard/ipc-agent/issues/7.
-    async fn subscribe(&self, method: &str) -> Result<Receiver<Value>>;
+    /// Sends the `requests`, each made of a method and its params, as a single JSON-RPC batch.
+    /// The results are returned in the same order as the requests. The failure of a request
+    /// does not fail the others.
+    async fn batch_request(&self, requests: Vec<(String, Value)>) -> Result<Vec<Result<Value>>>;
+
+    /// Subscribes to notifications via a Websocket by sending a JSON-RPC request with `method`
+    /// and `params`. The returned [`Subscription`] holds the response to the request and the
+    /// channel that receives the notifications sent by the server. If the connection is lost, the
+    /// client reconnects and subscribes again, until the subscription is cancelled or dropped.
+    /// See: https://github.com/consensus-shipyard/ipc-agent/issues/7.
+    async fn subscribe(&self, method: &str, params: Value) -> Result<Subscription>;
 }
 
 /// The implementation of [`JsonRpcClient`].
 pub struct JsonRpcClientImpl {
     http_client: Client,
     url: Url,
+    /// The websocket url. If set, requests and subscriptions are sent through it, otherwise
+    /// requests are sent to `url` via HTTP/HTTPS and subscriptions are made to `url`. Requests
+    /// fall back to HTTP/HTTPS while the websocket endpoint is unavailable.
+    ws_url: Option<Url>,
     bearer_token: Option<String>,
+    /// The id of the next request. Each request gets its own id, so that responses can be
+    /// matched to their requests.
+    next_id: AtomicU64,
+    /// The websocket connection shared by all requests, opened on the first request.
+    ws_connection: Mutex<Option<Arc<WsConnection>>>,
+    options: RequestOptions,
+    /// The time until which each endpoint is considered unhealthy, by index: the main url is
+    /// at 0 and the fallback urls follow.
+    unhealthy_until: StdMutex<HashMap<usize, Instant>>,
+    /// The time until which the websocket endpoint is considered unhealthy.
+    ws_unhealthy_until: StdMutex<Option<Instant>>,
+}
+
+/// The options of the requests sent by a [`JsonRpcClientImpl`]. The retries and the fallback
+/// urls only apply to the requests sent via HTTP/HTTPS.
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    /// The timeout of each request, either via HTTP/HTTPS or websocket. No timeout is set on the
+    /// HTTP/HTTPS requests if `None`, while the websocket requests use a default timeout.
+    pub request_timeout: Option<Duration>,
+    /// The number of times a request is retried after a connection error.
+    pub max_retries: u32,
+    /// The time to wait before retrying a request. It is doubled after each retry.
+    pub retry_backoff: Duration,
+    /// The urls to send requests to when the main url is unhealthy, in order of preference.
+    pub fallback_urls: Vec<Url>,
+    /// How long an endpoint is considered unhealthy after a connection error.
+    pub unhealthy_cooldown: Duration,
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: None,
+            max_retries: 0,
+            retry_backoff: Duration::from_secs(1),
+            fallback_urls: vec![],
+            unhealthy_cooldown: DEFAULT_UNHEALTHY_COOLDOWN,
+        }
+    }
+}
+
+/// The failure of a request sent to one of the endpoints of a [`JsonRpcClientImpl`].
+enum EndpointError {
+    /// The endpoint could not serve the request, another one might.
+    Unavailable(anyhow::Error),
+    /// The request itself failed.
+    Request(anyhow::Error),
 }
 
 impl JsonRpcClientImpl {
@@ -54,56 +142,387 @@ impl JsonRpcClientImpl {
         Self {
             http_client: Client::default(),
             url,
+            ws_url: None,
             bearer_token: bearer_token.map(String::from),
+            next_id: AtomicU64::new(1),
+            ws_connection: Mutex::new(None),
+            options: RequestOptions::default(),
+            unhealthy_until: StdMutex::new(HashMap::new()),
+            ws_unhealthy_until: StdMutex::new(None),
         }
     }
-}
 
-#[async_trait]
-impl JsonRpcClient for JsonRpcClientImpl {
-    async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
-        let request_body = build_jsonrpc_request(method, params)?;
-        let mut builder = self.http_client.post(self.url.as_str()).json(&request_body);
+    /// Trusts the CA certificate in `pem`, in addition to the system ones, for the requests sent
+    /// via HTTPS.
+    pub fn with_root_certificate(mut self, pem: &[u8]) -> Result<Self> {
+        let certificate = reqwest::Certificate::from_pem(pem)?;
+        self.http_client = Client::builder()
+            .add_root_certificate(certificate)
+            .build()?;
+        Ok(self)
+    }
+
+    /// Sets the options of the requests.
+    pub fn with_options(mut self, options: RequestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the websocket `url`. Requests are then multiplexed over a single websocket connection
+    /// to `url` instead of being sent to the url passed to [`JsonRpcClientImpl::new`]. If the
+    /// connection cannot be opened or fails before a request is sent, the request is sent via
+    /// HTTP/HTTPS instead and the websocket endpoint is left alone for the unhealthy cooldown.
+    pub fn with_ws_url(mut self, url: Url) -> Self {
+        self.ws_url = Some(url);
+        self
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Sends the request to the preferred healthy endpoint, retrying with the next one if the
+    // endpoint is unavailable.
+    async fn http_request(&self, request_body: Value) -> Result<Value> {
+        let mut backoff = self.options.retry_backoff;
+        let mut retries = 0;
+        loop {
+            let endpoint = self.pick_endpoint();
+            let url = self.endpoint_url(endpoint);
+            match self.http_request_to(url, &request_body).await {
+                Ok(response) => return Ok(response),
+                Err(EndpointError::Request(e)) => return Err(e),
+                Err(EndpointError::Unavailable(e)) => {
+                    log::warn!("endpoint {url} unavailable: {e:}");
+                    self.mark_unhealthy(endpoint);
+                    if retries >= self.options.max_retries {
+                        return Err(e);
+                    }
+                }
+            }
+
+            retries += 1;
+            sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    async fn http_request_to(
+        &self,
+        url: &Url,
+        request_body: &Value,
+    ) -> Result<Value, EndpointError> {
+        let mut builder = self.http_client.post(url.as_str()).json(request_body);
 
         // Add the authorization bearer token if present
-        if self.bearer_token.is_some() {
-            builder = builder.bearer_auth(self.bearer_token.as_ref().unwrap());
+        if let Some(token) = &self.bearer_token {
+            builder = builder.bearer_auth(token);
+        }
+        if let Some(timeout) = self.options.request_timeout {
+            builder = builder.timeout(timeout);
         }
 
-        let response = builder.send().await?;
+        let response = builder.send().await.map_err(|e| {
+            // The request is not retried on timeouts, as it might have been executed anyway.
+            if e.is_connect() {
+                EndpointError::Unavailable(e.into())
+            } else {
+                EndpointError::Request(e.into())
+            }
+        })?;
+        // Nor is it retried on server errors, as it reached the server and might have been
+        // executed, e.g. a message pushed to the mpool before the server failed.
+        if response.status().is_server_error() {
+            return Err(EndpointError::Request(anyhow!(
+                "server error: {}",
+                response.status()
+            )));
+        }
 
-        let response_body = response.text().await?;
+        let response_body = response
+            .text()
+            .await
+            .map_err(|e| EndpointError::Request(e.into()))?;
         log::debug!("received raw response body: {:?}", response_body);
 
-        let value = serde_json::from_str::<JsonRpcResponse<T>>(response_body.as_ref())?;
+        serde_json::from_str(response_body.as_ref()).map_err(|e| EndpointError::Request(e.into()))
+    }
 
-        if value.id != DEFAULT_JSON_RPC_ID || value.jsonrpc != DEFAULT_JSON_RPC_VERSION {
-            return Err(anyhow!("json_rpc id or version not matching."));
+    // Returns the index of the endpoint to send the next request to. It is the first healthy
+    // one, the main url first. If all the endpoints are unhealthy, it is the one that will
+    // recover first.
+    fn pick_endpoint(&self) -> usize {
+        let unhealthy_until = lock(&self.unhealthy_until);
+        let now = Instant::now();
+
+        let endpoints = 0..=self.options.fallback_urls.len();
+        let healthy = endpoints
+            .clone()
+            .find(|i| unhealthy_until.get(i).map_or(true, |until| *until <= now));
+        healthy.unwrap_or_else(|| {
+            endpoints
+                .min_by_key(|i| unhealthy_until.get(i).copied())
+                .unwrap_or(0)
+        })
+    }
+
+    fn endpoint_url(&self, endpoint: usize) -> &Url {
+        match endpoint {
+            0 => &self.url,
+            i => &self.options.fallback_urls[i - 1],
         }
+    }
+
+    fn mark_unhealthy(&self, endpoint: usize) {
+        let until = Instant::now() + self.options.unhealthy_cooldown;
+        lock(&self.unhealthy_until).insert(endpoint, until);
+    }
+
+    // Sends `request_body`, which holds the requests with `ids`, through the websocket endpoint
+    // if there is a healthy one, and via HTTP/HTTPS otherwise.
+    async fn send_request(&self, ids: &[u64], request_body: Value) -> Result<Value> {
+        if let Some(url) = self.healthy_ws_url() {
+            match self.ws_request(url, ids, &request_body).await {
+                Ok(response) => return Ok(response),
+                Err(EndpointError::Request(e)) => return Err(e),
+                Err(EndpointError::Unavailable(e)) => {
+                    log::warn!("websocket endpoint {url} unavailable, falling back to http: {e:}");
+                    let until = Instant::now() + self.options.unhealthy_cooldown;
+                    *lock(&self.ws_unhealthy_until) = Some(until);
+                }
+            }
+        }
+        self.http_request(request_body).await
+    }
+
+    fn healthy_ws_url(&self) -> Option<&Url> {
+        let unhealthy_until = *lock(&self.ws_unhealthy_until);
+        match unhealthy_until {
+            Some(until) if until > Instant::now() => None,
+            _ => self.ws_url.as_ref(),
+        }
+    }
+
+    async fn ws_request(
+        &self,
+        url: &Url,
+        ids: &[u64],
+        request_body: &Value,
+    ) -> Result<Value, EndpointError> {
+        let connection = self
+            .ws_connection(url)
+            .await
+            .map_err(EndpointError::Unavailable)?;
+        let timeout = self
+            .options
+            .request_timeout
+            .unwrap_or(DEFAULT_WS_REQUEST_TIMEOUT);
+        let mut responses = connection.batch_request(ids, request_body, timeout).await?;
+        log::debug!("received raw responses: {:?}", responses);
+
+        if request_body.is_array() {
+            Ok(Value::Array(responses))
+        } else {
+            Ok(responses.remove(0))
+        }
+    }
+
+    // Returns the shared websocket connection, opening it again if it was closed.
+    async fn ws_connection(&self, url: &Url) -> Result<Arc<WsConnection>> {
+        if let Some(connection) = self.ws_connection.lock().await.as_ref() {
+            if !connection.is_closed() {
+                return Ok(connection.clone());
+            }
+        }
+
+        // The lock is not held while connecting, so that a stalling endpoint does not block the
+        // other requests beyond their own timeout.
+        let connect_timeout = self
+            .options
+            .request_timeout
+            .unwrap_or(DEFAULT_WS_CONNECT_TIMEOUT);
+        let connect = WsConnection::connect(url, self.bearer_token.as_deref());
+        let connection = tokio::time::timeout(connect_timeout, connect)
+            .await
+            .map_err(|_| anyhow!("websocket connection timed out after {connect_timeout:?}"))??;
+        let connection = Arc::new(connection);
+
+        // Another request might have opened a connection in the meantime, in which case it is
+        // kept and the new one is dropped.
+        let mut ws_connection = self.ws_connection.lock().await;
+        match ws_connection.as_ref() {
+            Some(current) if !current.is_closed() => Ok(current.clone()),
+            _ => {
+                *ws_connection = Some(connection.clone());
+                Ok(connection)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for JsonRpcClientImpl {
+    async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let id = self.next_id();
+        let request_body = build_jsonrpc_request(id, method, params)?;
+
+        let response_body = self.send_request(&[id], request_body).await?;
+
+        let value = serde_json::from_value::<JsonRpcResponse<T>>(response_body)?;
+        value.check(id)?;
 
         Result::from(value)
     }
 
-    async fn subscribe(&self, method: &str) -> Result<Receiver<Value>> {
-        let mut request = self.url.as_str().into_client_request()?;
+    async fn batch_request(&self, requests: Vec<(String, Value)>) -> Result<Vec<Result<Value>>> {
+        // An empty batch is not a valid JSON-RPC request.
+        if requests.is_empty() {
+            return Ok(vec![]);
+        }
 
-        // Add the authorization bearer token if present
-        if self.bearer_token.is_some() {
-            let token_string = format!("Bearer {}", self.bearer_token.as_ref().unwrap());
-            let header_value = HeaderValue::from_str(token_string.as_str())?;
-            request.headers_mut().insert("Authorization", header_value);
+        let ids = requests.iter().map(|_| self.next_id()).collect::<Vec<_>>();
+        let request_body = ids
+            .iter()
+            .zip(requests)
+            .map(|(id, (method, params))| build_jsonrpc_request(*id, &method, params))
+            .collect::<Result<Vec<_>>>()?;
+        let request_body = Value::Array(request_body);
+
+        let responses = match self.send_request(&ids, request_body).await? {
+            Value::Array(responses) => responses,
+            // The server answers with a single response if the whole batch is rejected.
+            response => {
+                let response = serde_json::from_value::<JsonRpcResponse<Value>>(response)?;
+                Result::from(response)?;
+                return Err(anyhow!("json_rpc batch response is not an array"));
+            }
+        };
+
+        // The responses might not be in the same order as the requests, so they are matched by id.
+        // A response with a null id holds an error the server could not attribute to a request,
+        // as it could not read its id. An invalid response only fails its own request.
+        let mut responses_by_id = HashMap::new();
+        let mut unattributed_error = None;
+        for response in responses {
+            let response = match serde_json::from_value::<JsonRpcResponse<Value>>(response) {
+                Ok(response) => response,
+                Err(e) => {
+                    log::warn!("skipping invalid json_rpc batch response: {e:}");
+                    continue;
+                }
+            };
+            match response.id {
+                Some(id) => {
+                    responses_by_id.insert(id, response);
+                }
+                None => {
+                    if let Some(error) = response.error {
+                        unattributed_error.get_or_insert(error);
+                    }
+                }
+            }
         }
 
-        let (mut ws_stream, _) = connect_async(request).await?;
-        let request_body = build_jsonrpc_request(method, NO_PARAMS)?;
-        ws_stream
-            .send(Message::text(request_body.to_string()))
-            .await?;
+        // If none of the requests got a response, the error is the one of the whole batch.
+        if let Some(error) = &unattributed_error {
+            if responses_by_id.is_empty() {
+                return Err(error.clone().into());
+            }
+        }
+
+        let results = ids
+            .iter()
+            .map(|id| match responses_by_id.remove(id) {
+                Some(response) if response.jsonrpc == DEFAULT_JSON_RPC_VERSION => {
+                    Result::from(response)
+                }
+                Some(_) => Err(anyhow!("json_rpc version not matching.")),
+                None => match &unattributed_error {
+                    Some(error) => Err(error.clone().into()),
+                    None => Err(anyhow!("json_rpc batch response missing request id {id}")),
+                },
+            })
+            .collect();
+        Ok(results)
+    }
+
+    async fn subscribe(&self, method: &str, params: Value) -> Result<Subscription> {
+        let request = SubscriptionRequest {
+            url: self.ws_url.as_ref().unwrap_or(&self.url).clone(),
+            bearer_token: self.bearer_token.clone(),
+            id: self.next_id(),
+            method: String::from(method),
+            params,
+        };
+
+        // The first attempt to subscribe is not retried, so that the caller learns about
+        // misconfigurations right away.
+        let (ws_stream, response) = open_subscription(&request).await?;
 
         let (send_chan, recv_chan) = async_channel::unbounded::<Value>();
-        spawn(handle_stream(ws_stream, send_chan));
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        spawn(handle_subscription(
+            request,
+            ws_stream,
+            response.clone(),
+            send_chan,
+            cancel_rx,
+        ));
 
-        Ok(recv_chan)
+        Ok(Subscription {
+            response,
+            notifications: recv_chan,
+            cancel: cancel_tx,
+        })
+    }
+}
+
+/// A subscription to the notifications of a JSON-RPC server, returned by
+/// [`JsonRpcClient::subscribe`].
+pub struct Subscription {
+    response: Value,
+    notifications: Receiver<Value>,
+    cancel: oneshot::Sender<()>,
+}
+
+impl Subscription {
+    /// Creates a subscription from its parts. The subscription is cancelled when `cancel` is
+    /// triggered or dropped.
+    pub fn new(
+        response: Value,
+        notifications: Receiver<Value>,
+        cancel: oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            response,
+            notifications,
+            cancel,
+        }
+    }
+
+    /// Returns the result of the subscription request, e.g. the id of the channel in Lotus.
+    pub fn response(&self) -> &Value {
+        &self.response
+    }
+
+    /// Returns the channel that receives the notifications.
+    pub fn notifications(&self) -> &Receiver<Value> {
+        &self.notifications
+    }
+
+    /// Receives the next notification. It fails once the subscription has ended.
+    pub async fn recv(&self) -> Result<Value> {
+        self.notifications
+            .recv()
+            .await
+            .map_err(|_| anyhow!("subscription ended"))
+    }
+
+    /// Cancels the subscription and closes its connection. Dropping the subscription has the
+    /// same effect.
+    pub fn cancel(self) {
+        // The subscription might have ended already, in which case there is nothing to cancel.
+        let _ = self.cancel.send(());
     }
 }
 
@@ -112,20 +531,37 @@ impl JsonRpcClient for JsonRpcClientImpl {
 /// them into Result.
 #[derive(Debug, Deserialize)]
 struct JsonRpcResponse<T> {
-    id: u8,
+    /// The id of the request. It is null if the server could not read it, e.g. because the
+    /// request could not be parsed, in which case the response holds the error.
+    id: Option<u64>,
     jsonrpc: String,
 
     result: Option<T>,
-    error: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+impl<T> JsonRpcResponse<T> {
+    /// Checks that the response answers the request with `id`, or reports an error the server
+    /// could not attribute to any request.
+    fn check(&self, id: u64) -> Result<()> {
+        let id_matches = match self.id {
+            Some(response_id) => response_id == id,
+            None => self.error.is_some(),
+        };
+        if !id_matches || self.jsonrpc != DEFAULT_JSON_RPC_VERSION {
+            return Err(anyhow!("json_rpc id or version not matching."));
+        }
+        Ok(())
+    }
 }
 
 impl<T: DeserializeOwned> From<JsonRpcResponse<T>> for Result<T> {
     fn from(j: JsonRpcResponse<T>) -> Self {
-        if j.error.is_some() {
-            return Err(anyhow!("json_rpc error: {:}", j.error.unwrap()));
+        if let Some(error) = j.error {
+            return Err(error.into());
         }
-        if j.result.is_some() {
-            Ok(j.result.unwrap())
+        if let Some(result) = j.result {
+            Ok(result)
         } else {
             // The result is not found, but it is possible T could be the rust unit type: (), i.e. the
             // caller is expecting Result<()>.
@@ -135,37 +571,359 @@ impl<T: DeserializeOwned> From<JsonRpcResponse<T>> for Result<T> {
     }
 }
 
-// Processes a websocket stream by reading messages from the stream `ws_stream` and sending
-// them to an output channel `chan`.
-async fn handle_stream(
-    mut ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
-    chan: Sender<Value>,
+/// What is needed to open a subscription again after losing its connection.
+struct SubscriptionRequest {
+    url: Url,
+    bearer_token: Option<String>,
+    id: u64,
+    method: String,
+    params: Value,
+}
+
+/// A websocket connection over which concurrent requests are multiplexed. Responses are matched
+/// to their pending requests by id.
+struct WsConnection {
+    sink: Mutex<SplitSink<WsStream, Message>>,
+    /// The requests waiting for a response, by id.
+    pending: Arc<StdMutex<HashMap<u64, oneshot::Sender<Value>>>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl WsConnection {
+    async fn connect(url: &Url, bearer_token: Option<&str>) -> Result<Self> {
+        let (ws_stream, _) = connect_async(ws_client_request(url, bearer_token)?).await?;
+        let (sink, stream) = ws_stream.split();
+
+        let pending = Arc::new(StdMutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        spawn(dispatch_responses(stream, pending.clone(), closed.clone()));
+
+        Ok(Self {
+            sink: Mutex::new(sink),
+            pending,
+            closed,
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    // Sends `request_body`, which holds the requests with `ids`, and waits for all their
+    // responses within `timeout`. The responses are returned in the same order as `ids`.
+    // The connection is unavailable if the requests cannot be sent, but they are not sent again
+    // once sent, as they might have been executed.
+    async fn batch_request(
+        &self,
+        ids: &[u64],
+        request_body: &Value,
+        timeout: Duration,
+    ) -> Result<Vec<Value>, EndpointError> {
+        let mut recv_chans = Vec::with_capacity(ids.len());
+        {
+            let mut pending = lock(&self.pending);
+            for id in ids {
+                let (send_chan, recv_chan) = oneshot::channel();
+                pending.insert(*id, send_chan);
+                recv_chans.push((*id, recv_chan));
+            }
+        }
+
+        // The connection is checked after registering the requests, so that either the requests
+        // are failed here or they are failed by the task dispatching the responses when it stops.
+        if self.is_closed() {
+            self.remove_pending(ids);
+            return Err(EndpointError::Unavailable(anyhow!(
+                "websocket connection closed"
+            )));
+        }
+
+        let responses = tokio::time::timeout(timeout, self.send_and_wait(request_body, recv_chans))
+            .await
+            .unwrap_or_else(|_| {
+                Err(EndpointError::Request(anyhow!(
+                    "request timed out after {timeout:?}"
+                )))
+            });
+        // The requests are given up on failure, so that their entries do not linger.
+        if responses.is_err() {
+            self.remove_pending(ids);
+        }
+        responses
+    }
+
+    async fn send_and_wait(
+        &self,
+        request_body: &Value,
+        recv_chans: Vec<(u64, oneshot::Receiver<Value>)>,
+    ) -> Result<Vec<Value>, EndpointError> {
+        let sent = self
+            .sink
+            .lock()
+            .await
+            .send(Message::text(request_body.to_string()))
+            .await;
+        if let Err(e) = sent {
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(EndpointError::Unavailable(e.into()));
+        }
+
+        let mut responses = Vec::with_capacity(recv_chans.len());
+        for (id, recv_chan) in recv_chans {
+            let response = recv_chan.await.map_err(|_| {
+                EndpointError::Request(anyhow!(
+                    "websocket connection closed before response to request {id}"
+                ))
+            })?;
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+
+    fn remove_pending(&self, ids: &[u64]) {
+        let mut pending = lock(&self.pending);
+        for id in ids {
+            pending.remove(id);
+        }
+    }
+}
+
+// Reads the responses from a shared websocket connection and hands each of them to the request
+// with the same id. When the connection ends, all the pending requests are failed.
+async fn dispatch_responses(
+    mut stream: SplitStream<WsStream>,
+    pending: Arc<StdMutex<HashMap<u64, oneshot::Sender<Value>>>>,
+    closed: Arc<AtomicBool>,
 ) {
-    loop {
-        match ws_stream.next().await {
-            None => {
-                log::trace!("No message in websocket stream. The stream was closed.");
+    while let Some(msg) = stream.next().await {
+        let value = match msg.map_err(anyhow::Error::from).and_then(parse_message) {
+            Ok(Some(value)) => value,
+            Ok(None) => continue,
+            Err(e) => {
+                log::warn!("websocket connection interrupted: {e:}");
                 break;
             }
-            Some(result) => match result {
-                Ok(msg) => {
-                    println!("{}", msg);
-                    log::trace!("Read message from websocket stream: {}", msg);
-                    let value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
-                    chan.send(value).await.unwrap();
+        };
+
+        // The responses to a batch of requests arrive together in an array.
+        let responses = match value {
+            Value::Array(responses) => responses,
+            value => vec![value],
+        };
+        let mut unattributed_errors = vec![];
+        for response in responses {
+            let id = response.get("id").and_then(Value::as_u64);
+            if id.is_none() && response.get("error").is_some() {
+                unattributed_errors.push(response);
+                continue;
+            }
+
+            let request = id.and_then(|id| lock(&pending).remove(&id));
+            match request {
+                // The request might have been given up, in which case the response is dropped.
+                Some(request) => {
+                    let _ = request.send(response);
                 }
-                Err(err) => {
-                    log::error!("Error reading message from websocket stream: {:?}", err);
-                    break;
+                None => log::trace!("skipping message without pending request: {response}"),
+            }
+        }
+
+        // An error with a null id is sent when the server cannot read the id of a request. As
+        // the request cannot be told apart, all the pending ones are failed with the error
+        // instead of waiting for a response that will not arrive.
+        for error in unattributed_errors {
+            log::warn!("received json_rpc error without id: {error}");
+            let requests = lock(&pending).drain().collect::<Vec<_>>();
+            for (_, request) in requests {
+                let _ = request.send(error.clone());
+            }
+        }
+    }
+
+    // Dropping the pending requests lets them know that no response will arrive.
+    closed.store(true, Ordering::SeqCst);
+    lock(&pending).clear();
+}
+
+fn lock<T>(mutex: &StdMutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // The lock is never held across a panic, but recover from poisoning anyway.
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Builds the request to open a websocket connection to `url`.
+fn ws_client_request(url: &Url, bearer_token: Option<&str>) -> Result<Request> {
+    let mut ws_request = url.as_str().into_client_request()?;
+
+    // Add the authorization bearer token if present
+    if let Some(token) = bearer_token {
+        let token_string = format!("Bearer {token}");
+        let header_value = HeaderValue::from_str(token_string.as_str())?;
+        ws_request
+            .headers_mut()
+            .insert("Authorization", header_value);
+    }
+
+    Ok(ws_request)
+}
+
+// Connects to the websocket server and sends the subscription request. It returns the stream
+// along with the result of the subscription request.
+async fn open_subscription(request: &SubscriptionRequest) -> Result<(WsStream, Value)> {
+    let ws_request = ws_client_request(&request.url, request.bearer_token.as_deref())?;
+    let (mut ws_stream, _) = connect_async(ws_request).await?;
+    let request_body = build_jsonrpc_request(request.id, &request.method, request.params.clone())?;
+    ws_stream
+        .send(Message::text(request_body.to_string()))
+        .await?;
+
+    // Wait for the response to the subscription request, notifications are not expected before it.
+    while let Some(msg) = ws_stream.next().await {
+        let value = match parse_message(msg?)? {
+            Some(value) => value,
+            None => continue,
+        };
+        if value.get("method").is_some() {
+            log::trace!("skipping notification received before subscription response: {value}");
+            continue;
+        }
+
+        let response = serde_json::from_value::<JsonRpcResponse<Value>>(value)?;
+        response.check(request.id)?;
+        let result = Result::from(response)?;
+        return Ok((ws_stream, result));
+    }
+
+    Err(anyhow!(
+        "websocket stream closed before subscription response"
+    ))
+}
+
+// Forwards the notifications of a subscription to `chan` until the subscription is cancelled or
+// `chan` is closed. When the connection is lost, it subscribes again with exponential backoff.
+async fn handle_subscription(
+    request: SubscriptionRequest,
+    mut ws_stream: WsStream,
+    mut response: Value,
+    chan: Sender<Value>,
+    mut cancel: oneshot::Receiver<()>,
+) {
+    let mut backoff = INITIAL_RECONNECT_BACKOFF;
+
+    'subscription: loop {
+        let result = select! {
+            r = forward_notifications(&mut ws_stream, &response, &chan) => r,
+            _ = &mut cancel => {
+                log::debug!("subscription to {:} cancelled", request.method);
+                if let Err(e) = ws_stream.close(None).await {
+                    log::debug!("cannot close websocket stream: {e:}");
                 }
-            },
+                break 'subscription;
+            }
         };
+
+        match result {
+            Ok(()) => {
+                log::debug!("subscription to {:} no longer received", request.method);
+                break 'subscription;
+            }
+            Err(e) => log::warn!("subscription to {:} interrupted: {e:}", request.method),
+        }
+
+        // Subscribe again, waiting longer after each failed attempt.
+        loop {
+            log::info!(
+                "resubscribing to {:} in {:} seconds",
+                request.method,
+                backoff.as_secs()
+            );
+            let reconnected = select! {
+                r = async {
+                    sleep(backoff).await;
+                    open_subscription(&request).await
+                } => r,
+                _ = &mut cancel => {
+                    log::debug!("subscription to {:} cancelled", request.method);
+                    break 'subscription;
+                }
+            };
+            backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+
+            match reconnected {
+                Ok((s, r)) => {
+                    ws_stream = s;
+                    response = r;
+                    backoff = INITIAL_RECONNECT_BACKOFF;
+                    break;
+                }
+                Err(e) => log::warn!("cannot resubscribe to {:}: {e:}", request.method),
+            }
+        }
     }
+
     chan.close();
 }
 
+// Reads the messages of `ws_stream` and sends the notifications to `chan`. `response` is the
+// result of the subscription request, which Lotus uses as the id of the channel whose values are
+// notified. It returns `Ok` if `chan` is closed, and an error if the subscription is interrupted.
+async fn forward_notifications(
+    ws_stream: &mut WsStream,
+    response: &Value,
+    chan: &Sender<Value>,
+) -> Result<()> {
+    while let Some(msg) = ws_stream.next().await {
+        let value = match parse_message(msg?)? {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let method = value.get("method").and_then(Value::as_str);
+        let notification = match (method, value.get("params")) {
+            // Lotus notifies the values of a channel with params `[channel id, value]`
+            (Some(XRPC_CHANNEL_VALUE), Some(Value::Array(params)))
+                if params.len() == 2 && params[0] == *response =>
+            {
+                params[1].clone()
+            }
+            (Some(XRPC_CHANNEL_CLOSE), Some(Value::Array(params)))
+                if params.first() == Some(response) =>
+            {
+                return Err(anyhow!("channel closed by the server"));
+            }
+            (Some(XRPC_CHANNEL_VALUE | XRPC_CHANNEL_CLOSE), _) => {
+                log::trace!("skipping message of another channel: {value}");
+                continue;
+            }
+            (Some(_), Some(params)) => params.clone(),
+            _ => {
+                log::trace!("skipping message that is not a notification: {value}");
+                continue;
+            }
+        };
+
+        if chan.send(notification).await.is_err() {
+            return Ok(());
+        }
+    }
+
+    Err(anyhow!("websocket stream closed"))
+}
+
+// Parses a websocket message into a JSON value. It returns `None` for messages that carry no
+// data, such as pings, and an error if the message closes the connection.
+fn parse_message(msg: Message) -> Result<Option<Value>> {
+    match msg {
+        Message::Text(text) => {
+            log::trace!("read message from websocket stream: {text}");
+            Ok(Some(serde_json::from_str(&text)?))
+        }
+        Message::Close(frame) => Err(anyhow!("websocket closed by the server: {frame:?}")),
+        _ => Ok(None),
+    }
+}
+
 // A convenience function to build a JSON-RPC request.
-fn build_jsonrpc_request(method: &str, params: Value) -> Result<Value> {
+fn build_jsonrpc_request(id: u64, method: &str, params: Value) -> Result<Value> {
     let has_params = if params.is_array() {
         let array_params = params.as_array().unwrap();
         !array_params.is_empty()
@@ -181,14 +939,14 @@ fn build_jsonrpc_request(method: &str, params: Value) -> Result<Value> {
     let request_value = if has_params {
         json!({
             "jsonrpc": DEFAULT_JSON_RPC_VERSION,
-            "id": DEFAULT_JSON_RPC_ID,
+            "id": id,
             "method": method,
             "params": params,
         })
     } else {
         json!({
             "jsonrpc": DEFAULT_JSON_RPC_VERSION,
-            "id": DEFAULT_JSON_RPC_ID,
+            "id": id,
             "method": method,
         })
     };
@@ -1,10 +1,21 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use futures_util::StreamExt;
-use serde_json::json;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
 use url::Url;
+use warp::Filter;
 
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl, NO_PARAMS};
+use crate::jsonrpc::{
+    lock, JsonRpcClient, JsonRpcClientImpl, JsonRpcError, RequestOptions, NO_PARAMS,
+};
+use crate::lotus::mock::{MockChain, MockLotus};
 
 /// The default endpoints for public lotus node. If the urls fail in running tests, need to
 /// check these endpoints again.
@@ -23,6 +34,73 @@ async fn test_request() {
     assert!(response.get("Height").is_some());
 }
 
+#[test]
+fn test_request_ids() {
+    let url = Url::parse(HTTP_ENDPOINT).unwrap();
+    let client = JsonRpcClientImpl::new(url, None);
+    let a = client.next_id();
+    let b = client.next_id();
+    assert_ne!(a, b);
+}
+
+#[tokio::test]
+async fn test_concurrent_ws_requests() {
+    // The server waits for both requests and answers them in the reverse order.
+    let ws_url = ws_server(|mut ws_stream| async move {
+        let mut requests = vec![];
+        while requests.len() < 2 {
+            match ws_stream.next().await {
+                Some(Ok(Message::Text(text))) => {
+                    requests.push(serde_json::from_str::<Value>(&text).unwrap())
+                }
+                Some(Ok(_)) => continue,
+                _ => return,
+            }
+        }
+        for request in requests.into_iter().rev() {
+            let result = match request["method"].as_str().unwrap() {
+                "Filecoin.ChainHead" => json!({"Height": 10}),
+                _ => json!("/root"),
+            };
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+            ws_stream
+                .send(Message::text(response.to_string()))
+                .await
+                .unwrap();
+        }
+        while ws_stream.next().await.is_some() {}
+    })
+    .await;
+    let client = JsonRpcClientImpl::new(ws_url.clone(), None).with_ws_url(ws_url);
+
+    let (head, name) = tokio::join!(
+        client.request::<Value>("Filecoin.ChainHead", NO_PARAMS),
+        client.request::<String>("Filecoin.StateNetworkName", NO_PARAMS),
+    );
+    assert_eq!(head.unwrap()["Height"], 10);
+    assert_eq!(name.unwrap(), "/root");
+}
+
+#[tokio::test]
+#[ignore]
+async fn test_batch_request() {
+    let url = Url::parse(HTTP_ENDPOINT).unwrap();
+    let client = JsonRpcClientImpl::new(url, None);
+    let results = client
+        .batch_request(vec![
+            (String::from("Filecoin.ChainHead"), NO_PARAMS),
+            (String::from("Filecoin.ChainGetBlock"), NO_PARAMS),
+            (String::from("Filecoin.StateNetworkName"), NO_PARAMS),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].as_ref().unwrap().get("Height").is_some());
+    // Missing params only fail their own request
+    assert!(results[1].is_err());
+    assert_eq!(results[2].as_ref().unwrap(), "mainnet");
+}
+
 #[tokio::test]
 async fn test_request_error() {
     let url = Url::parse(HTTP_ENDPOINT).unwrap();
@@ -31,7 +109,8 @@ async fn test_request_error() {
     let response = client
         .request::<serde_json::Value>("Filecoin.ChainGetBlock", NO_PARAMS)
         .await;
-    assert!(response.is_err());
+    let error = response.unwrap_err();
+    assert!(error.downcast_ref::<JsonRpcError>().is_some());
 }
 
 #[tokio::test]
@@ -63,8 +142,169 @@ async fn test_request_with_params_error() {
 async fn test_subscribe() {
     let url = Url::parse(WS_ENDPOINT).unwrap();
     let client = JsonRpcClientImpl::new(url, None);
-    let mut chan = client.subscribe("Filecoin.ChainNotify").await.unwrap();
+    let subscription = client
+        .subscribe("Filecoin.ChainNotify", NO_PARAMS)
+        .await
+        .unwrap();
+    assert!(subscription.response().is_number());
     for _ in 1..=3 {
-        chan.next().await.unwrap();
+        let changes = subscription.recv().await.unwrap();
+        assert!(changes.is_array());
     }
+    subscription.cancel();
+}
+
+/// Starts a websocket server that hands each connection to `serve`, and returns its url.
+async fn ws_server<F, Fut>(serve: F) -> Url
+where
+    F: Fn(WebSocketStream<TcpStream>) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+            tokio::spawn(serve(ws_stream));
+        }
+    });
+    Url::parse(&format!("ws://{addr}")).unwrap()
+}
+
+/// Starts a websocket server that accepts connections but never answers the requests, and
+/// returns its url.
+async fn silent_ws_server() -> Url {
+    ws_server(|mut ws_stream| async move { while ws_stream.next().await.is_some() {} }).await
+}
+
+/// Starts an http server that answers every request with `response`, and returns its url.
+fn http_server(response: Value) -> Url {
+    let filter = warp::post().map(move || warp::reply::json(&response));
+    let (addr, server) = warp::serve(filter).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    Url::parse(&format!("http://{addr}")).unwrap()
+}
+
+/// The error a server sends with a null id when it cannot read the id of a request.
+fn invalid_request_error() -> Value {
+    json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "invalid request"}})
+}
+
+#[tokio::test]
+async fn test_ws_request_timeout() {
+    let ws_url = silent_ws_server().await;
+    let options = RequestOptions {
+        request_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    let client = JsonRpcClientImpl::new(ws_url.clone(), None)
+        .with_options(options)
+        .with_ws_url(ws_url);
+
+    let response = client
+        .request::<serde_json::Value>("Filecoin.ChainHead", NO_PARAMS)
+        .await;
+    assert!(response.is_err());
+
+    // The request given up is no longer pending.
+    let connection = client.ws_connection.lock().await.clone().unwrap();
+    assert!(lock(&connection.pending).is_empty());
+}
+
+#[tokio::test]
+async fn test_batch_request_out_of_order() {
+    let mut chain = MockChain::new("/root");
+    chain.height = 10;
+    chain.reverse_batches = true;
+    let lotus = MockLotus::start(chain);
+    let client = JsonRpcClientImpl::new(lotus.url(), None);
+
+    let results = client
+        .batch_request(vec![
+            (String::from("Filecoin.StateNetworkName"), NO_PARAMS),
+            (String::from("Filecoin.Unknown"), NO_PARAMS),
+            (String::from("Filecoin.ChainHead"), NO_PARAMS),
+        ])
+        .await
+        .unwrap();
+
+    // The responses are matched to their requests, and an error only fails its own request.
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), "/root");
+    let error = results[1].as_ref().unwrap_err();
+    assert!(error.downcast_ref::<JsonRpcError>().is_some());
+    assert_eq!(results[2].as_ref().unwrap()["Height"], 10);
+}
+
+#[tokio::test]
+async fn test_null_id_errors() {
+    let is_invalid_request = |e: &anyhow::Error| {
+        e.downcast_ref::<JsonRpcError>()
+            .map_or(false, |e| e.message == "invalid request")
+    };
+
+    // The error of a single request is reported as is.
+    let client = JsonRpcClientImpl::new(http_server(invalid_request_error()), None);
+    let error = client
+        .request::<Value>("Filecoin.ChainHead", NO_PARAMS)
+        .await
+        .unwrap_err();
+    assert!(is_invalid_request(&error));
+
+    // The error of a whole batch is reported as the error of the batch.
+    let client = JsonRpcClientImpl::new(http_server(json!([invalid_request_error()])), None);
+    let requests = vec![
+        (String::from("Filecoin.ChainHead"), NO_PARAMS),
+        (String::from("Filecoin.StateNetworkName"), NO_PARAMS),
+    ];
+    let error = client.batch_request(requests.clone()).await.unwrap_err();
+    assert!(is_invalid_request(&error));
+
+    // Over websocket, the pending requests are failed with the error instead of waiting.
+    let ws_url = ws_server(|mut ws_stream| async move {
+        while let Some(Ok(_)) = ws_stream.next().await {
+            let error = Message::text(invalid_request_error().to_string());
+            ws_stream.send(error).await.unwrap();
+        }
+    })
+    .await;
+    let client = JsonRpcClientImpl::new(ws_url.clone(), None).with_ws_url(ws_url);
+    let error = client
+        .request::<Value>("Filecoin.ChainHead", NO_PARAMS)
+        .await
+        .unwrap_err();
+    assert!(is_invalid_request(&error));
+    let error = client.batch_request(requests).await.unwrap_err();
+    assert!(is_invalid_request(&error));
+}
+
+#[tokio::test]
+async fn test_server_error_not_retried() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let filter = warp::post().map({
+        let hits = hits.clone();
+        move || {
+            hits.fetch_add(1, Ordering::SeqCst);
+            warp::reply::with_status("", warp::http::StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    });
+    let (addr, server) = warp::serve(filter).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    // The fallback would answer, but the failed request might have been executed already.
+    let lotus = MockLotus::start(MockChain::new("/root"));
+    let options = RequestOptions {
+        max_retries: 2,
+        retry_backoff: Duration::from_millis(10),
+        fallback_urls: vec![lotus.url()],
+        ..Default::default()
+    };
+    let url = Url::parse(&format!("http://{addr}")).unwrap();
+    let client = JsonRpcClientImpl::new(url, None).with_options(options);
+
+    let response = client
+        .request::<Value>("Filecoin.MpoolPushMessage", NO_PARAMS)
+        .await;
+    assert!(response.is_err());
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
 }
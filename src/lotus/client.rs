@@ -1,10 +1,12 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use async_channel::Receiver;
 use async_trait::async_trait;
 use cid::Cid;
 use fvm_shared::address::Address;
@@ -14,10 +16,12 @@ use ipc_gateway::Checkpoint;
 use ipc_sdk::subnet_id::SubnetID;
 use num_traits::cast::ToPrimitive;
 use serde::de::DeserializeOwned;
-use serde_json::json;
+use serde_json::{json, Value};
 
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl, NO_PARAMS};
-use crate::lotus::message::chain::ChainHeadResponse;
+use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl, RequestOptions, NO_PARAMS};
+use crate::lotus::message::chain::{
+    ChainGetTipSetByHeightResponse, ChainHeadResponse, ChainNotifyResponse, TipSetKey,
+};
 use crate::lotus::message::ipc::{
     IPCGetPrevCheckpointForChildResponse, IPCReadGatewayStateResponse,
     IPCReadSubnetActorStateResponse,
@@ -43,11 +47,84 @@ mod methods {
     pub const WALLET_DEFAULT_ADDRESS: &str = "Filecoin.WalletDefaultAddress";
     pub const STATE_READ_STATE: &str = "Filecoin.StateReadState";
     pub const CHAIN_HEAD: &str = "Filecoin.ChainHead";
+    pub const CHAIN_GET_TIPSET_BY_HEIGHT: &str = "Filecoin.ChainGetTipSetByHeight";
+    pub const CHAIN_NOTIFY: &str = "Filecoin.ChainNotify";
     pub const IPC_GET_PREV_CHECKPOINT_FOR_CHILD: &str = "Filecoin.IPCGetPrevCheckpointForChild";
     pub const IPC_GET_CHECKPOINT_TEMPLATE: &str = "Filecoin.IPCGetCheckpointTemplate";
     pub const IPC_READ_GATEWAY_STATE: &str = "Filecoin.IPCReadGatewayState";
     pub const IPC_READ_SUBNET_ACTOR_STATE: &str = "Filecoin.IPCReadSubnetActorState";
     pub const IPC_LIST_CHILD_SUBNETS: &str = "Filecoin.IPCListChildSubnets";
+    pub const IPC_HAS_VOTED_BOTTOM_UP_CHECKPOINT: &str = "Filecoin.IPCHasVotedBottomUpCheckpoint";
+}
+
+// The params of the requests that can be sent either alone or in a batch.
+mod params {
+    use anyhow::{anyhow, Result};
+    use fvm_shared::address::Address;
+    use fvm_shared::clock::ChainEpoch;
+    use ipc_sdk::subnet_id::SubnetID;
+    use serde_json::{json, Value};
+
+    use super::GATEWAY_ACTOR_ADDRESS;
+    use crate::lotus::message::chain::TipSetKey;
+
+    pub fn chain_get_tipset_by_height(height: ChainEpoch) -> Value {
+        // refer to: https://lotus.filecoin.io/reference/lotus/chain/#chaingettipsetbyheight
+        // An empty tip set key looks the height up from the current head.
+        json!([height, []])
+    }
+
+    pub fn ipc_get_prev_checkpoint_for_child(child_subnet_id: &SubnetID) -> Result<Value> {
+        let parent = match child_subnet_id.parent() {
+            None => return Err(anyhow!("The child_subnet_id must be a valid child subnet")),
+            Some(parent) => parent,
+        };
+        let subnet_actor = child_subnet_id.subnet_actor().to_string();
+        Ok(json!([GATEWAY_ACTOR_ADDRESS, {"Parent": parent.to_string(), "Actor": subnet_actor}]))
+    }
+
+    pub fn ipc_get_checkpoint_template(epoch: ChainEpoch, tip_set: &TipSetKey) -> Value {
+        json!([GATEWAY_ACTOR_ADDRESS, epoch, tip_set.to_cid_maps()])
+    }
+
+    pub fn ipc_read_gateway_state(tip_set: &TipSetKey) -> Value {
+        json!([GATEWAY_ACTOR_ADDRESS, tip_set.to_cid_maps()])
+    }
+
+    pub fn ipc_read_subnet_actor_state(subnet_id: &SubnetID, tip_set: &TipSetKey) -> Result<Value> {
+        let parent = subnet_id
+            .parent()
+            .ok_or_else(|| anyhow!("no parent found"))?
+            .to_string();
+        let actor = subnet_id.subnet_actor().to_string();
+        Ok(json!([
+            {
+                "Parent": parent,
+                "Actor": actor
+            },
+            tip_set.to_cid_maps()]
+        ))
+    }
+
+    pub fn ipc_has_voted_bottom_up_checkpoint(
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<Value> {
+        let parent = subnet_id
+            .parent()
+            .ok_or_else(|| anyhow!("no parent found"))?
+            .to_string();
+        let actor = subnet_id.subnet_actor().to_string();
+        Ok(json!([
+            {
+                "Parent": parent,
+                "Actor": actor
+            },
+            epoch,
+            validator.to_string()
+        ]))
+    }
 }
 
 /// The default gateway actor address
@@ -85,6 +162,14 @@ impl<T: JsonRpcClient> LotusJsonRPCClient<T> {
     pub fn new(client: T) -> Self {
         Self { client }
     }
+
+    /// Returns a builder of a batch of requests, sent to the node in a single round trip.
+    pub fn batch(&self) -> LotusBatch<'_, T> {
+        LotusBatch {
+            client: &self.client,
+            requests: vec![],
+        }
+    }
 }
 
 #[async_trait]
@@ -232,17 +317,17 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
         Ok(r)
     }
 
-    async fn read_state<State: DeserializeOwned + Debug>(
+    async fn state_read_state(
         &self,
         address: Address,
-        tipset: Cid,
-    ) -> Result<ReadStateResponse<State>> {
+        tip_set: &TipSetKey,
+    ) -> Result<ReadStateResponse<Value>> {
         // refer to: https://lotus.filecoin.io/reference/lotus/state/#statereadstate
         let r = self
             .client
-            .request::<ReadStateResponse<State>>(
+            .request::<ReadStateResponse<Value>>(
                 methods::STATE_READ_STATE,
-                json!([address.to_string(), [CIDMap::from(tipset)]]),
+                json!([address.to_string(), tip_set.to_cid_maps()]),
             )
             .await?;
         log::debug!("received read_state response: {r:?}");
@@ -258,17 +343,51 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
         Ok(r)
     }
 
+    async fn chain_get_tipset_by_height(
+        &self,
+        height: ChainEpoch,
+    ) -> Result<ChainGetTipSetByHeightResponse> {
+        let params = params::chain_get_tipset_by_height(height);
+
+        let r = self
+            .client
+            .request::<ChainGetTipSetByHeightResponse>(methods::CHAIN_GET_TIPSET_BY_HEIGHT, params)
+            .await?;
+        log::debug!("received chain_get_tipset_by_height response: {r:?}");
+        Ok(r)
+    }
+
+    async fn chain_notify(&self) -> Result<Receiver<ChainNotifyResponse>> {
+        let subscription = self
+            .client
+            .subscribe(methods::CHAIN_NOTIFY, NO_PARAMS)
+            .await?;
+        let (send_chan, recv_chan) = async_channel::unbounded::<ChainNotifyResponse>();
+
+        // The subscription is cancelled when dropped, i.e. once nobody receives the changes.
+        tokio::spawn(async move {
+            while let Ok(value) = subscription.recv().await {
+                match serde_json::from_value::<ChainNotifyResponse>(value) {
+                    Ok(changes) => {
+                        log::debug!("received chain_notify changes: {changes:?}");
+                        if send_chan.send(changes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => log::warn!("cannot decode chain_notify changes: {e:}"),
+                }
+            }
+            send_chan.close();
+        });
+
+        Ok(recv_chan)
+    }
+
     async fn ipc_get_prev_checkpoint_for_child(
         &self,
         child_subnet_id: SubnetID,
     ) -> Result<IPCGetPrevCheckpointForChildResponse> {
-        let parent = match child_subnet_id.parent() {
-            None => return Err(anyhow!("The child_subnet_id must be a valid child subnet")),
-            Some(parent) => parent,
-        };
-        let subnet_actor = child_subnet_id.subnet_actor().to_string();
-        let params =
-            json!([GATEWAY_ACTOR_ADDRESS, {"Parent": parent.to_string(), "Actor": subnet_actor}]);
+        let params = params::ipc_get_prev_checkpoint_for_child(&child_subnet_id)?;
 
         let r = self
             .client
@@ -280,19 +399,26 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
         Ok(r)
     }
 
-    async fn ipc_get_checkpoint_template(&self, epoch: ChainEpoch) -> Result<Checkpoint> {
+    async fn ipc_get_checkpoint_template(
+        &self,
+        epoch: ChainEpoch,
+        tip_set: &TipSetKey,
+    ) -> Result<Checkpoint> {
         let r = self
             .client
             .request::<Checkpoint>(
                 methods::IPC_GET_CHECKPOINT_TEMPLATE,
-                json!([GATEWAY_ACTOR_ADDRESS, epoch]),
+                params::ipc_get_checkpoint_template(epoch, tip_set),
             )
             .await?;
         Ok(r)
     }
 
-    async fn ipc_read_gateway_state(&self, tip_set: Cid) -> Result<IPCReadGatewayStateResponse> {
-        let params = json!([GATEWAY_ACTOR_ADDRESS, [CIDMap::from(tip_set)]]);
+    async fn ipc_read_gateway_state(
+        &self,
+        tip_set: &TipSetKey,
+    ) -> Result<IPCReadGatewayStateResponse> {
+        let params = params::ipc_read_gateway_state(tip_set);
         let r = self
             .client
             .request::<IPCReadGatewayStateResponse>(methods::IPC_READ_GATEWAY_STATE, params)
@@ -303,20 +429,9 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
     async fn ipc_read_subnet_actor_state(
         &self,
         subnet_id: &SubnetID,
-        tip_set: Cid,
+        tip_set: &TipSetKey,
     ) -> Result<IPCReadSubnetActorStateResponse> {
-        let parent = subnet_id
-            .parent()
-            .ok_or_else(|| anyhow!("no parent found"))?
-            .to_string();
-        let actor = subnet_id.subnet_actor().to_string();
-        let params = json!([
-            {
-                "Parent": parent,
-                "Actor": actor
-            },
-            [CIDMap::from(tip_set)]]
-        );
+        let params = params::ipc_read_subnet_actor_state(subnet_id, tip_set)?;
         log::debug!("sending {params:?}");
 
         let r = self
@@ -329,6 +444,39 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
         Ok(r)
     }
 
+    async fn ipc_has_voted_bottom_up_checkpoint(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool> {
+        let params = params::ipc_has_voted_bottom_up_checkpoint(subnet_id, epoch, validator)?;
+
+        let r = self
+            .client
+            .request::<bool>(methods::IPC_HAS_VOTED_BOTTOM_UP_CHECKPOINT, params)
+            .await?;
+        log::debug!("received ipc_has_voted_bottom_up_checkpoint response: {r:?}");
+        Ok(r)
+    }
+
+    async fn ipc_has_voted_bottom_up_checkpoints(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validators: &[Address],
+    ) -> Result<Vec<bool>> {
+        // All the validators are queried in a single round trip to the node.
+        let mut batch = self.batch();
+        let votes = validators
+            .iter()
+            .map(|validator| batch.ipc_has_voted_bottom_up_checkpoint(subnet_id, epoch, validator))
+            .collect::<Result<Vec<_>>>()?;
+        let mut response = batch.send().await?;
+
+        votes.into_iter().map(|vote| response.get(vote)).collect()
+    }
+
     async fn ipc_list_child_subnets(&self, gateway_addr: Address) -> Result<Vec<SubnetInfo>> {
         let params = json!([gateway_addr]);
         let r = self
@@ -341,11 +489,155 @@ impl<T: JsonRpcClient + Send + Sync> LotusClient for LotusJsonRPCClient<T> {
 
 impl LotusJsonRPCClient<JsonRpcClientImpl> {
     /// A constructor that returns a `LotusJsonRPCClient` from a `Subnet`. The returned
-    /// `LotusJsonRPCClient` makes requests to the URL defined in the `Subnet`.
+    /// `LotusJsonRPCClient` makes requests to the URLs defined in the `Subnet`, following its
+    /// timeout and retry settings. If the `Subnet` has a websocket URL, requests and
+    /// subscriptions go through it instead, and requests fall back to the HTTP URLs while the
+    /// websocket is unavailable.
     pub fn from_subnet(subnet: &crate::config::Subnet) -> Self {
         let url = subnet.jsonrpc_api_http.clone();
         let auth_token = subnet.auth_token.as_deref();
-        let jsonrpc_client = JsonRpcClientImpl::new(url, auth_token);
+        let options = RequestOptions {
+            request_timeout: subnet.request_timeout.map(Duration::from_secs),
+            max_retries: subnet.max_retries,
+            retry_backoff: Duration::from_secs(subnet.retry_backoff),
+            fallback_urls: subnet.jsonrpc_api_http_fallbacks.clone(),
+            ..Default::default()
+        };
+        let mut jsonrpc_client = JsonRpcClientImpl::new(url, auth_token).with_options(options);
+        if let Some(ws_url) = &subnet.jsonrpc_api_ws {
+            jsonrpc_client = jsonrpc_client.with_ws_url(ws_url.clone());
+        }
         LotusJsonRPCClient::new(jsonrpc_client)
     }
 }
+
+/// A typed handle to the result of a request added to a [`LotusBatch`].
+pub struct BatchItem<R> {
+    index: usize,
+    _result: PhantomData<R>,
+}
+
+/// A builder of a batch of Lotus requests that are sent in a single round trip. Adding a request
+/// returns a [`BatchItem`] to get its typed result from the [`BatchResponse`].
+/// # Examples
+/// ```no_run
+/// use ipc_agent::{jsonrpc::JsonRpcClientImpl, lotus::client::LotusJsonRPCClient};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let h = JsonRpcClientImpl::new("<DEFINE YOUR URL HERE>".parse()?, None);
+///     let n = LotusJsonRPCClient::new(h);
+///
+///     let mut batch = n.batch();
+///     let head = batch.chain_head();
+///     let tip_set = batch.chain_get_tipset_by_height(100);
+///     let mut response = batch.send().await?;
+///
+///     println!("head: {:?}", response.get(head)?);
+///     println!("tip set at height 100: {:?}", response.get(tip_set)?);
+///     Ok(())
+/// }
+/// ```
+pub struct LotusBatch<'a, T: JsonRpcClient> {
+    client: &'a T,
+    requests: Vec<(String, Value)>,
+}
+
+impl<'a, T: JsonRpcClient> LotusBatch<'a, T> {
+    fn add<R>(&mut self, method: &str, params: Value) -> BatchItem<R> {
+        self.requests.push((String::from(method), params));
+        BatchItem {
+            index: self.requests.len() - 1,
+            _result: PhantomData,
+        }
+    }
+
+    /// See [`LotusClient::chain_head`].
+    pub fn chain_head(&mut self) -> BatchItem<ChainHeadResponse> {
+        self.add(methods::CHAIN_HEAD, NO_PARAMS)
+    }
+
+    /// See [`LotusClient::chain_get_tipset_by_height`].
+    pub fn chain_get_tipset_by_height(
+        &mut self,
+        height: ChainEpoch,
+    ) -> BatchItem<ChainGetTipSetByHeightResponse> {
+        let params = params::chain_get_tipset_by_height(height);
+        self.add(methods::CHAIN_GET_TIPSET_BY_HEIGHT, params)
+    }
+
+    /// See [`LotusClient::ipc_get_prev_checkpoint_for_child`].
+    pub fn ipc_get_prev_checkpoint_for_child(
+        &mut self,
+        child_subnet_id: &SubnetID,
+    ) -> Result<BatchItem<IPCGetPrevCheckpointForChildResponse>> {
+        let params = params::ipc_get_prev_checkpoint_for_child(child_subnet_id)?;
+        Ok(self.add(methods::IPC_GET_PREV_CHECKPOINT_FOR_CHILD, params))
+    }
+
+    /// See [`LotusClient::ipc_get_checkpoint_template`].
+    pub fn ipc_get_checkpoint_template(
+        &mut self,
+        epoch: ChainEpoch,
+        tip_set: &TipSetKey,
+    ) -> BatchItem<Checkpoint> {
+        let params = params::ipc_get_checkpoint_template(epoch, tip_set);
+        self.add(methods::IPC_GET_CHECKPOINT_TEMPLATE, params)
+    }
+
+    /// See [`LotusClient::ipc_read_gateway_state`].
+    pub fn ipc_read_gateway_state(
+        &mut self,
+        tip_set: &TipSetKey,
+    ) -> BatchItem<IPCReadGatewayStateResponse> {
+        let params = params::ipc_read_gateway_state(tip_set);
+        self.add(methods::IPC_READ_GATEWAY_STATE, params)
+    }
+
+    /// See [`LotusClient::ipc_read_subnet_actor_state`].
+    pub fn ipc_read_subnet_actor_state(
+        &mut self,
+        subnet_id: &SubnetID,
+        tip_set: &TipSetKey,
+    ) -> Result<BatchItem<IPCReadSubnetActorStateResponse>> {
+        let params = params::ipc_read_subnet_actor_state(subnet_id, tip_set)?;
+        Ok(self.add(methods::IPC_READ_SUBNET_ACTOR_STATE, params))
+    }
+
+    /// See [`LotusClient::ipc_has_voted_bottom_up_checkpoint`].
+    pub fn ipc_has_voted_bottom_up_checkpoint(
+        &mut self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<BatchItem<bool>> {
+        let params = params::ipc_has_voted_bottom_up_checkpoint(subnet_id, epoch, validator)?;
+        Ok(self.add(methods::IPC_HAS_VOTED_BOTTOM_UP_CHECKPOINT, params))
+    }
+
+    /// Sends all the requests of the batch at once.
+    pub async fn send(self) -> Result<BatchResponse> {
+        let results = self.client.batch_request(self.requests).await?;
+        log::debug!("received batch response: {results:?}");
+        Ok(BatchResponse {
+            results: results.into_iter().map(Some).collect(),
+        })
+    }
+}
+
+/// The results of the requests of a [`LotusBatch`].
+pub struct BatchResponse {
+    results: Vec<Option<Result<Value>>>,
+}
+
+impl BatchResponse {
+    /// Returns the result of the request that returned `item` when added to the batch.
+    pub fn get<R: DeserializeOwned>(&mut self, item: BatchItem<R>) -> Result<R> {
+        let result = self
+            .results
+            .get_mut(item.index)
+            .and_then(Option::take)
+            .ok_or_else(|| anyhow!("no result in batch response for request {}", item.index))?;
+        Ok(serde_json::from_value(result?)?)
+    }
+}
@@ -1,5 +1,10 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use cid::Cid;
 use serde::Deserialize;
 use serde_json::Value;
 
@@ -16,3 +21,142 @@ pub struct ChainHeadResponse {
     #[allow(dead_code)]
     pub height: u64,
 }
+
+impl ChainHeadResponse {
+    /// Returns the key of the tip set, made of the CIDs of all its blocks.
+    pub fn tip_set_key(&self) -> anyhow::Result<TipSetKey> {
+        TipSetKey::try_from(self.cids.clone())
+    }
+}
+
+/// A `ChainGetTipSetByHeight` response has the same shape as a `ChainHead` one, as both return a
+/// tip set.
+pub type ChainGetTipSetByHeightResponse = ChainHeadResponse;
+
+/// A change of the head of the chain, as notified by a `ChainNotify` subscription. The first
+/// notification has type `current` and holds the current head. The following ones have type
+/// `apply` or `revert`, for tip sets added to or removed from the chain.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HeadChange {
+    #[serde(rename = "Type")]
+    pub change_type: String,
+    pub val: ChainHeadResponse,
+}
+
+/// Each `ChainNotify` notification carries all the head changes that happened at once.
+pub type ChainNotifyResponse = Vec<HeadChange>;
+
+/// The key of a tip set, i.e. the CIDs of all the blocks in the tip set. A tip set can contain
+/// more than one block, so the key must not be reduced to a single CID.
+/// See: https://github.com/filecoin-project/lotus/blob/master/chain/types/tipset_key.go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipSetKey {
+    cids: Vec<Cid>,
+}
+
+impl TipSetKey {
+    /// Creates a new `TipSetKey` from the CIDs of the blocks of a tip set.
+    pub fn new(cids: Vec<Cid>) -> anyhow::Result<Self> {
+        if cids.is_empty() {
+            return Err(anyhow!("tip set key must contain at least one cid"));
+        }
+        Ok(Self { cids })
+    }
+
+    pub fn cids(&self) -> &[Cid] {
+        &self.cids
+    }
+
+    /// Returns the bytes of the key, which are the concatenation of the bytes of its CIDs, the
+    /// same way as Lotus encodes them.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.cids.iter().flat_map(|c| c.to_bytes()).collect()
+    }
+
+    /// Returns the key in the format expected by the Lotus json rpc api.
+    pub fn to_cid_maps(&self) -> Vec<CIDMap> {
+        self.cids.iter().map(|c| CIDMap::from(*c)).collect()
+    }
+}
+
+impl TryFrom<Vec<CIDMap>> for TipSetKey {
+    type Error = anyhow::Error;
+
+    fn try_from(cid_maps: Vec<CIDMap>) -> Result<Self, Self::Error> {
+        let cids = cid_maps
+            .into_iter()
+            .map(Cid::try_from)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        TipSetKey::new(cids)
+    }
+}
+
+/// Parses a comma separated list of CIDs into a `TipSetKey`.
+impl FromStr for TipSetKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cids = s
+            .split(',')
+            .map(|c| Cid::from_str(c.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        TipSetKey::new(cids)
+    }
+}
+
+impl Display for TipSetKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let cids = self.cids.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", cids.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cid::Cid;
+
+    use crate::lotus::message::chain::{ChainNotifyResponse, TipSetKey};
+    use crate::lotus::message::CIDMap;
+
+    const CID_A: &str = "bafy2bzacecwgnejfzcq7a4zvvownmb4oae6xzyu323z5wuuufesbtikortt6k";
+    const CID_B: &str = "bafy2bzaceazwhb53nykdikj2vhnx5vfi2ij3bnqo2wlb643m3jqzqzgkdnpcw";
+
+    #[test]
+    fn test_tip_set_key_multiple_blocks() {
+        let key = TipSetKey::from_str(&format!("{CID_A},{CID_B}")).unwrap();
+        let a = Cid::from_str(CID_A).unwrap();
+        let b = Cid::from_str(CID_B).unwrap();
+        assert_eq!(key.cids(), &[a, b]);
+
+        let mut bytes = a.to_bytes();
+        bytes.extend(b.to_bytes());
+        assert_eq!(key.to_bytes(), bytes);
+
+        let key2 = TipSetKey::try_from(key.to_cid_maps()).unwrap();
+        assert_eq!(key, key2);
+        assert_eq!(key.to_string(), format!("{CID_A},{CID_B}"));
+    }
+
+    #[test]
+    fn test_chain_notify_response() {
+        let raw = format!(
+            r#"[{{"Type":"apply","Val":{{"Cids":[{{"/":"{CID_A}"}},{{"/":"{CID_B}"}}],"Blocks":[{{}},{{}}],"Height":42}}}}]"#
+        );
+        let changes: ChainNotifyResponse = serde_json::from_str(&raw).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].change_type, "apply");
+        assert_eq!(changes[0].val.height, 42);
+
+        let key = changes[0].val.tip_set_key().unwrap();
+        assert_eq!(key.to_string(), format!("{CID_A},{CID_B}"));
+    }
+
+    #[test]
+    fn test_tip_set_key_empty() {
+        assert!(TipSetKey::new(vec![]).is_err());
+        assert!(TipSetKey::try_from(Vec::<CIDMap>::new()).is_err());
+    }
+}
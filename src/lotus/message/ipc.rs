@@ -4,6 +4,7 @@ use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use ipc_gateway::Status;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::lotus::message::CIDMap;
@@ -33,15 +34,21 @@ pub struct IPCReadSubnetActorStateResponse {
 }
 
 /// SubnetInfo is an auxiliary struct that collects relevant information about the state of a subnet
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Its JSON schema leaves the fields of the foreign types other than the id unconstrained.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SubnetInfo {
     /// Id of the subnet.
+    #[schemars(with = "String")]
     pub id: SubnetID,
     /// Collateral staked in the subnet.
+    #[schemars(with = "serde_json::Value")]
     pub stake: TokenAmount,
     /// Circulating supply available in the subnet.
+    #[schemars(with = "serde_json::Value")]
     pub circ_supply: TokenAmount,
     /// State of the Subnet (Initialized, Active, Killed)
+    #[schemars(with = "serde_json::Value")]
     pub status: Status,
 }
 
@@ -49,7 +56,7 @@ pub struct SubnetInfo {
 /// In the actor, it is `Deserialize_tuple`, but when returned from json rpc endpoints, it's
 /// actually `json` struct. The deserialization is not working because the agent is interpreting
 /// the tuple as json.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct ValidatorSet {
     pub validators: Option<Vec<Validator>>,
     // sequence number that uniquely identifies a validator set
@@ -57,7 +64,7 @@ pub struct ValidatorSet {
 }
 
 /// The validator struct. See `ValidatorSet` comment on why we need this duplicated definition.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, JsonSchema)]
 pub struct Validator {
     pub addr: String,
     pub net_addr: String,
@@ -53,6 +53,7 @@ impl MpoolPushMessageResponseInner {
     }
 }
 
+#[derive(Clone)]
 pub struct MpoolPushMessage {
     pub to: Address,
     pub from: Address,
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 use anyhow::anyhow;
 use base64::Engine;
+use cid::Cid;
 use fil_actors_runtime::cbor;
 use fvm_ipld_encoding::RawBytes;
 use serde::de::DeserializeOwned;
@@ -9,19 +10,42 @@ use serde::Deserialize;
 
 use crate::lotus::message::CIDMap;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct StateWaitMsgResponse {
-    #[allow(dead_code)]
     message: CIDMap,
-    #[allow(dead_code)]
     pub(crate) receipt: Receipt,
-    #[allow(dead_code)]
     tip_set: Vec<CIDMap>,
-    #[allow(dead_code)]
     height: u64,
 }
 
+impl StateWaitMsgResponse {
+    /// Returns the CID of the message that was waited for.
+    pub fn message_cid(&self) -> anyhow::Result<Cid> {
+        Cid::try_from(self.message.clone())
+    }
+
+    /// Returns the exit code of the message execution.
+    pub fn exit_code(&self) -> u32 {
+        self.receipt.exit_code
+    }
+
+    /// Returns the gas consumed by the message execution.
+    pub fn gas_used(&self) -> u64 {
+        self.receipt.gas_used
+    }
+
+    /// Returns the CIDs of the blocks of the tip set in which the message was executed.
+    pub fn tip_set(&self) -> anyhow::Result<Vec<Cid>> {
+        self.tip_set.iter().cloned().map(Cid::try_from).collect()
+    }
+
+    /// Returns the height of the tip set in which the message was executed.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ReadStateResponse<State> {
@@ -33,21 +57,29 @@ pub struct ReadStateResponse<State> {
     pub state: State,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Receipt {
-    #[allow(dead_code)]
     exit_code: u32,
     #[serde(rename = "Return")]
     pub result: String,
-    #[allow(dead_code)]
     gas_used: u64,
 }
 
 impl Receipt {
-    pub fn parse_result_into<T: DeserializeOwned>(self) -> anyhow::Result<T> {
+    /// Returns the exit code of the message execution. It is zero if the execution succeeded.
+    pub fn exit_code(&self) -> u32 {
+        self.exit_code
+    }
+
+    /// Returns the gas consumed by the message execution.
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    pub fn parse_result_into<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
         let r = base64::engine::general_purpose::STANDARD
-            .decode(self.result)
+            .decode(&self.result)
             .map_err(|e| {
                 log::error!("cannot base64 decode due to {e:?}");
                 anyhow!("cannot decode return string")
@@ -0,0 +1,480 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! An in-process fake Lotus node for tests. It serves the json rpc methods used by the agent from
+//! a scriptable in-memory chain, so that the agent can be tested offline.
+// Not every test uses every part of the mock.
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use cid::multihash::MultihashGeneric;
+use cid::Cid;
+use futures_util::{SinkExt, StreamExt};
+use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::MethodNum;
+use ipc_gateway::Checkpoint;
+use ipc_sdk::subnet_id::SubnetID;
+use ipc_subnet_actor::types::MANIFEST_ID;
+use serde_json::{json, Value};
+use tokio::select;
+use tokio::sync::{broadcast, oneshot};
+use url::Url;
+use warp::ws::{Message, WebSocket};
+use warp::Filter;
+
+use crate::config::Subnet;
+use crate::jsonrpc::JsonRpcClientImpl;
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::message::ipc::SubnetInfo;
+
+/// The dag-cbor codec, used for the CIDs of blocks and messages.
+const DAG_CBOR: u64 = 0x71;
+/// The identity multihash, which keeps the generated CIDs readable.
+const IDENTITY: u64 = 0x00;
+/// The network version reported by the mock.
+const NETWORK_VERSION: u32 = 18;
+/// The id of the channel through which the head changes of `Filecoin.ChainNotify` are notified.
+const CHAIN_NOTIFY_CHANNEL: u64 = 1;
+
+/// A subnet actor deployed in the chain of the mock.
+#[derive(Debug, Clone)]
+pub(crate) struct MockSubnetActor {
+    pub check_period: ChainEpoch,
+    pub validators: Vec<Address>,
+}
+
+/// A message pushed to the mpool of the mock.
+#[derive(Debug, Clone)]
+pub(crate) struct PushedMessage {
+    pub cid: Cid,
+    pub to: Address,
+    pub from: Address,
+    pub value: TokenAmount,
+    pub method: MethodNum,
+    pub params: Vec<u8>,
+}
+
+/// The receipt of the execution of a message, returned by `StateWaitMsg`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MockReceipt {
+    pub exit_code: u32,
+    /// The cbor encoded return value of the message.
+    pub return_data: Vec<u8>,
+    pub gas_used: u64,
+}
+
+/// The in-memory chain served by a [`MockLotus`]. Tests change it to script the behavior of the
+/// node.
+#[derive(Debug)]
+pub(crate) struct MockChain {
+    pub network_name: String,
+    /// The height of the chain head. Every height has a tip set, there are no null rounds.
+    pub height: ChainEpoch,
+    /// The number of blocks of each tip set.
+    pub blocks_per_tip_set: usize,
+    /// Whether the responses to a batch are sent in the reverse order of the requests, as
+    /// JSON-RPC servers may answer the requests of a batch in any order.
+    pub reverse_batches: bool,
+    pub default_wallet: Address,
+    pub wallets: Vec<Address>,
+    pub subnet_actor_code_cid: Cid,
+    pub gateway_check_period: ChainEpoch,
+    pub subnet_actors: HashMap<SubnetID, MockSubnetActor>,
+    pub child_subnets: Vec<SubnetInfo>,
+    /// The CID returned by `IPCGetPrevCheckpointForChild`. It is replaced by the one of
+    /// [`checkpoint_cid`] when a checkpoint submission is waited for with `StateWaitMsg`, as if
+    /// the checkpoint was committed when executed.
+    pub prev_checkpoint: Cid,
+    /// The bottom-up checkpoint votes, by subnet, epoch and validator.
+    pub votes: HashSet<(SubnetID, ChainEpoch, Address)>,
+    /// The messages pushed to the mpool, in order.
+    pub mpool: Vec<PushedMessage>,
+    /// The receipts of the next messages pushed to the mpool. A message gets a successful
+    /// receipt with no return value if there is none left.
+    pub next_receipts: VecDeque<MockReceipt>,
+    receipts: HashMap<Cid, (MockReceipt, ChainEpoch)>,
+    /// The messages already executed, i.e. waited for.
+    executed: HashSet<Cid>,
+}
+
+impl MockChain {
+    pub fn new(network_name: &str) -> Self {
+        Self {
+            network_name: String::from(network_name),
+            height: 0,
+            blocks_per_tip_set: 1,
+            reverse_batches: false,
+            default_wallet: Address::new_id(100),
+            wallets: vec![Address::new_id(100)],
+            subnet_actor_code_cid: mock_cid("subnet actor code"),
+            gateway_check_period: 10,
+            subnet_actors: HashMap::new(),
+            child_subnets: vec![],
+            prev_checkpoint: mock_cid("genesis checkpoint"),
+            votes: HashSet::new(),
+            mpool: vec![],
+            next_receipts: VecDeque::new(),
+            receipts: HashMap::new(),
+            executed: HashSet::new(),
+        }
+    }
+
+    /// Returns the CIDs of the blocks of the tip set at `height`.
+    pub fn tip_set_cids(&self, height: ChainEpoch) -> Vec<Cid> {
+        (0..self.blocks_per_tip_set)
+            .map(|i| mock_cid(&format!("block {height} {i}")))
+            .collect()
+    }
+
+    fn tip_set(&self, height: ChainEpoch) -> Value {
+        let cids = self
+            .tip_set_cids(height)
+            .into_iter()
+            .map(|c| json!({"/": c.to_string()}))
+            .collect::<Vec<_>>();
+        let blocks = cids.iter().map(|_| json!({})).collect::<Vec<_>>();
+        json!({"Cids": cids, "Blocks": blocks, "Height": height})
+    }
+
+    fn subnet_actor(&self, params: &Value) -> Result<(&SubnetID, &MockSubnetActor)> {
+        let parent = params.get("Parent").and_then(Value::as_str);
+        let actor = params.get("Actor").and_then(Value::as_str);
+        self.subnet_actors
+            .iter()
+            .find(|(id, _)| {
+                id.parent().map(|p| p.to_string()).as_deref() == parent
+                    && Some(id.subnet_actor().to_string().as_str()) == actor
+            })
+            .ok_or_else(|| anyhow!("actor not found"))
+    }
+
+    fn mpool_push_message(&mut self, message: &Value) -> Result<Value> {
+        let field = |name: &str| {
+            message
+                .get(name)
+                .ok_or_else(|| anyhow!("missing message field: {name}"))
+        };
+        let to = Address::from_str(field("to")?.as_str().unwrap_or_default())?;
+        let from = Address::from_str(field("from")?.as_str().unwrap_or_default())?;
+        let value = field("value")?.as_str().unwrap_or("0").parse::<u64>()?;
+        let method = field("method")?
+            .as_u64()
+            .ok_or_else(|| anyhow!("invalid method"))?;
+        let params = serde_json::from_value::<Vec<u8>>(field("params")?.clone())?;
+
+        let nonce = self.mpool.len();
+        let cid = mock_cid(&format!("message {nonce}"));
+        let receipt = self.next_receipts.pop_front().unwrap_or_default();
+        self.receipts.insert(cid, (receipt, self.height));
+        self.mpool.push(PushedMessage {
+            cid,
+            to,
+            from,
+            value: TokenAmount::from_atto(value),
+            method,
+            params: params.clone(),
+        });
+
+        let cid = json!({"/": cid.to_string()});
+        Ok(json!({
+            "Message": {
+                "To": to.to_string(),
+                "From": from.to_string(),
+                "Value": value.to_string(),
+                "Method": method,
+                "Params": base64::engine::general_purpose::STANDARD.encode(params),
+                "Nonce": nonce,
+                "GasLimit": 0,
+                "GasFeeCap": "0",
+                "GasPremium": "0",
+                "Version": 0,
+                "CID": cid,
+            },
+            "CID": cid,
+        }))
+    }
+
+    fn state_wait_msg(&mut self, cid: &Value) -> Result<Value> {
+        let cid = Cid::from_str(cid.get("/").and_then(Value::as_str).unwrap_or_default())?;
+        let (receipt, height) = self
+            .receipts
+            .get(&cid)
+            .ok_or_else(|| anyhow!("message not found"))?;
+
+        // A successful checkpoint submission becomes the previous checkpoint of the next ones.
+        let is_checkpoint = self.mpool.iter().any(|m| {
+            m.cid == cid && m.method == ipc_subnet_actor::Method::SubmitCheckpoint as MethodNum
+        });
+        if is_checkpoint && receipt.exit_code == 0 && self.executed.insert(cid) {
+            self.prev_checkpoint = checkpoint_cid(&cid);
+        }
+        Ok(json!({
+            "Message": {"/": cid.to_string()},
+            "Receipt": {
+                "ExitCode": receipt.exit_code,
+                "Return": base64::engine::general_purpose::STANDARD.encode(&receipt.return_data),
+                "GasUsed": receipt.gas_used,
+            },
+            "TipSet": self.tip_set(*height)["Cids"],
+            "Height": height,
+        }))
+    }
+
+    // Serves a json rpc request, returning its result.
+    fn handle(&mut self, method: &str, params: &[Value]) -> Result<Value> {
+        let param = |i: usize| params.get(i).cloned().unwrap_or(Value::Null);
+        match method {
+            "Filecoin.StateNetworkName" => Ok(json!(self.network_name)),
+            "Filecoin.StateNetworkVersion" => Ok(json!(NETWORK_VERSION)),
+            "Filecoin.StateActorCodeCIDs" => Ok(json!({
+                MANIFEST_ID: {"/": self.subnet_actor_code_cid.to_string()}
+            })),
+            "Filecoin.WalletDefaultAddress" => Ok(json!(self.default_wallet.to_string())),
+            "Filecoin.WalletList" => Ok(json!(self
+                .wallets
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>())),
+            "Filecoin.WalletNew" => {
+                let address = Address::new_id(100 + self.wallets.len() as u64);
+                self.wallets.push(address);
+                Ok(json!(address.to_string()))
+            }
+            "Filecoin.ChainHead" => Ok(self.tip_set(self.height)),
+            "Filecoin.ChainGetTipSetByHeight" => {
+                let height = param(0).as_i64().ok_or_else(|| anyhow!("invalid height"))?;
+                if height > self.height {
+                    return Err(anyhow!("looking for tipset with height greater than head"));
+                }
+                Ok(self.tip_set(height))
+            }
+            "Filecoin.MpoolPushMessage" => self.mpool_push_message(&param(0)),
+            "Filecoin.StateWaitMsg" => self.state_wait_msg(&param(0)),
+            "Filecoin.IPCReadGatewayState" => Ok(json!({
+                "CheckPeriod": self.gateway_check_period
+            })),
+            "Filecoin.IPCReadSubnetActorState" => {
+                let (_, actor) = self.subnet_actor(&param(0))?;
+                let validators = actor
+                    .validators
+                    .iter()
+                    .map(|v| json!({"addr": v.to_string(), "net_addr": "", "weight": 1}))
+                    .collect::<Vec<_>>();
+                Ok(json!({
+                    "CheckPeriod": actor.check_period,
+                    "ValidatorSet": {"validators": validators, "configuration_number": 0},
+                }))
+            }
+            "Filecoin.IPCGetPrevCheckpointForChild" => {
+                self.subnet_actor(&param(1))?;
+                Ok(json!({"CID": {"/": self.prev_checkpoint.to_string()}}))
+            }
+            "Filecoin.IPCGetCheckpointTemplate" => {
+                let epoch = param(1).as_i64().ok_or_else(|| anyhow!("invalid epoch"))?;
+                let subnet = SubnetID::from_str(&self.network_name)?;
+                Ok(serde_json::to_value(Checkpoint::new(subnet, epoch))?)
+            }
+            "Filecoin.IPCHasVotedBottomUpCheckpoint" => {
+                let (subnet, _) = self.subnet_actor(&param(0))?;
+                let epoch = param(1).as_i64().ok_or_else(|| anyhow!("invalid epoch"))?;
+                let validator = Address::from_str(param(2).as_str().unwrap_or_default())?;
+                Ok(json!(self.votes.contains(&(
+                    subnet.clone(),
+                    epoch,
+                    validator
+                ))))
+            }
+            "Filecoin.IPCListChildSubnets" => Ok(serde_json::to_value(&self.child_subnets)?),
+            _ => Err(anyhow!("method '{method}' not found")),
+        }
+    }
+}
+
+/// A fake Lotus node serving a [`MockChain`] via HTTP and websocket on a local port. The node
+/// stops when dropped.
+pub(crate) struct MockLotus {
+    chain: Arc<Mutex<MockChain>>,
+    addr: SocketAddr,
+    /// Notifies the `Filecoin.ChainNotify` subscriptions of a head change.
+    head_changes: broadcast::Sender<()>,
+    _shutdown: oneshot::Sender<()>,
+}
+
+impl MockLotus {
+    /// Starts serving `chain` on a random local port.
+    pub fn start(chain: MockChain) -> Self {
+        let chain = Arc::new(Mutex::new(chain));
+        let (head_changes, _) = broadcast::channel(16);
+
+        let state = chain.clone();
+        let http = warp::post()
+            .and(warp::body::json())
+            .map(move |body: Value| {
+                let response = handle_body(&mut state.lock().unwrap(), body);
+                warp::reply::json(&response)
+            });
+        let state = chain.clone();
+        let changes = head_changes.clone();
+        let ws = warp::ws().map(move |ws: warp::ws::Ws| {
+            let (chain, changes) = (state.clone(), changes.subscribe());
+            ws.on_upgrade(move |socket| serve_ws(socket, chain, changes))
+        });
+        let filter = http.or(ws);
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let (addr, server) =
+            warp::serve(filter).bind_with_graceful_shutdown(([127, 0, 0, 1], 0), async move {
+                shutdown_rx.await.ok();
+            });
+        tokio::spawn(server);
+
+        Self {
+            chain,
+            addr,
+            head_changes,
+            _shutdown: shutdown_tx,
+        }
+    }
+
+    /// Notifies the `Filecoin.ChainNotify` subscriptions that the head of the chain changed.
+    pub fn notify_head_change(&self) {
+        // There might be no subscription yet.
+        let _ = self.head_changes.send(());
+    }
+
+    /// Returns the chain served by the node, to inspect or script it.
+    pub fn chain(&self) -> MutexGuard<'_, MockChain> {
+        self.chain.lock().unwrap()
+    }
+
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}/rpc/v0", self.addr)).unwrap()
+    }
+
+    pub fn ws_url(&self) -> Url {
+        Url::parse(&format!("ws://{}/rpc/v0", self.addr)).unwrap()
+    }
+
+    /// Returns the config of a subnet served by this node.
+    pub fn subnet(&self, accounts: Vec<Address>) -> Subnet {
+        Subnet {
+            id: SubnetID::from_str(&self.chain().network_name).unwrap(),
+            jsonrpc_api_http: self.url(),
+            jsonrpc_api_http_fallbacks: vec![],
+            jsonrpc_api_ws: None,
+            auth_token: None,
+            request_timeout: None,
+            max_retries: 0,
+            retry_backoff: 1,
+            accounts,
+        }
+    }
+
+    pub fn client(&self) -> LotusJsonRPCClient<JsonRpcClientImpl> {
+        LotusJsonRPCClient::new(JsonRpcClientImpl::new(self.url(), None))
+    }
+}
+
+fn handle_body(chain: &mut MockChain, body: Value) -> Value {
+    match body {
+        // A batch of requests gets a batch of responses
+        Value::Array(requests) => {
+            let mut responses = requests
+                .iter()
+                .map(|r| handle_request(chain, r))
+                .collect::<Vec<_>>();
+            if chain.reverse_batches {
+                responses.reverse();
+            }
+            Value::Array(responses)
+        }
+        request => handle_request(chain, &request),
+    }
+}
+
+/// Serves the requests received through a websocket connection. Once subscribed, the connection
+/// is notified of the head of the chain on each head change.
+async fn serve_ws(
+    socket: WebSocket,
+    chain: Arc<Mutex<MockChain>>,
+    mut head_changes: broadcast::Receiver<()>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let mut subscribed = false;
+    loop {
+        let reply = select! {
+            message = stream.next() => {
+                let body = match message {
+                    Some(Ok(message)) => match message.to_str().map(serde_json::from_str::<Value>) {
+                        Ok(Ok(body)) => body,
+                        _ => continue,
+                    },
+                    _ => return,
+                };
+                if body.get("method") == Some(&json!("Filecoin.ChainNotify")) {
+                    subscribed = true;
+                    json!({"jsonrpc": "2.0", "id": body["id"], "result": CHAIN_NOTIFY_CHANNEL})
+                } else {
+                    let mut chain = chain.lock().unwrap();
+                    handle_body(&mut chain, body)
+                }
+            }
+            changed = head_changes.recv() => {
+                match changed {
+                    Ok(()) if subscribed => {}
+                    Err(broadcast::error::RecvError::Closed) => return,
+                    _ => continue,
+                }
+                let chain = chain.lock().unwrap();
+                let change = json!({"Type": "apply", "Val": chain.tip_set(chain.height)});
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "xrpc.ch.val",
+                    "params": [CHAIN_NOTIFY_CHANNEL, [change]],
+                })
+            }
+        };
+        if sink.send(Message::text(reply.to_string())).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn handle_request(chain: &mut MockChain, request: &Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let params = match request.get("params") {
+        Some(Value::Array(params)) => params.clone(),
+        _ => vec![],
+    };
+
+    match chain.handle(method, &params) {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": 1, "message": e.to_string()},
+        }),
+    }
+}
+
+/// Returns the CID of the checkpoint submitted with the message `message_cid`.
+pub(crate) fn checkpoint_cid(message_cid: &Cid) -> Cid {
+    mock_cid(&format!("checkpoint {message_cid}"))
+}
+
+/// Returns a CID made from `s`, so that different strings give different CIDs.
+pub(crate) fn mock_cid(s: &str) -> Cid {
+    let hash = MultihashGeneric::<64>::wrap(IDENTITY, s.as_bytes()).unwrap();
+    Cid::new_v1(DAG_CBOR, hash)
+}
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 
 use anyhow::Result;
+use async_channel::Receiver;
 use async_trait::async_trait;
 use cid::Cid;
 use fvm_shared::address::Address;
@@ -12,8 +13,11 @@ use fvm_shared::clock::ChainEpoch;
 use ipc_gateway::Checkpoint;
 use ipc_sdk::subnet_id::SubnetID;
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 
-use message::chain::ChainHeadResponse;
+use message::chain::{
+    ChainGetTipSetByHeightResponse, ChainHeadResponse, ChainNotifyResponse, TipSetKey,
+};
 use message::mpool::{MpoolPushMessage, MpoolPushMessageResponseInner};
 use message::state::{ReadStateResponse, StateWaitMsgResponse};
 use message::wallet::{WalletKeyType, WalletListResponse};
@@ -27,15 +31,19 @@ use crate::manager::SubnetInfo;
 pub mod client;
 pub mod message;
 #[cfg(test)]
+pub(crate) mod mock;
+#[cfg(test)]
 mod tests;
 
 /// The network version of lotus network.
 /// see https://github.com/filecoin-project/go-state-types/blob/f6fd668a32b4b4a0bc39fd69d8a5f8fb11f49461/network/version.go#L7
 pub type NetworkVersion = u32;
 
-/// The Lotus client api to interact with the Lotus node.
+/// The Lotus client api to interact with the Lotus node. The trait is object-safe, so that
+/// the consumers of the api can work with any implementation behind a `dyn LotusClient`. The
+/// generic helpers built on top of it live in [`LotusClientExt`].
 #[async_trait]
-pub trait LotusClient {
+pub trait LotusClient: Send + Sync {
     /// Push the message to memory pool, see: https://lotus.filecoin.io/reference/lotus/mpool/#mpoolpushmessage
     async fn mpool_push_message(
         &self,
@@ -66,35 +74,105 @@ pub trait LotusClient {
     /// Create a new wallet, see: https://lotus.filecoin.io/reference/lotus/wallet/#walletnew
     async fn wallet_new(&self, key_type: WalletKeyType) -> Result<String>;
 
-    /// Read the state of the address at tipset, see: https://lotus.filecoin.io/reference/lotus/state/#statereadstate
-    async fn read_state<State: DeserializeOwned + Debug>(
+    /// Read the raw state of the address at tipset, see: https://lotus.filecoin.io/reference/lotus/state/#statereadstate
+    /// Use [`LotusClientExt::read_state`] to decode the state into a concrete type.
+    async fn state_read_state(
         &self,
         address: Address,
-        tipset: Cid,
-    ) -> Result<ReadStateResponse<State>>;
+        tip_set: &TipSetKey,
+    ) -> Result<ReadStateResponse<Value>>;
 
     /// Returns the current head of the chain.
     /// See: https://lotus.filecoin.io/reference/lotus/chain/#chainhead
     async fn chain_head(&self) -> Result<ChainHeadResponse>;
 
+    /// Returns the tip set at `height` in the current chain. If there is no tip set at `height`
+    /// (i.e. it was a null round), the tip set at the closest previous height is returned.
+    /// See: https://lotus.filecoin.io/reference/lotus/chain/#chaingettipsetbyheight
+    async fn chain_get_tipset_by_height(
+        &self,
+        height: ChainEpoch,
+    ) -> Result<ChainGetTipSetByHeightResponse>;
+
+    /// Subscribes to the changes of the head of the chain. The returned channel receives the
+    /// head changes as they are notified by the node, and is closed when the subscription ends.
+    /// See: https://lotus.filecoin.io/reference/lotus/chain/#chainnotify
+    async fn chain_notify(&self) -> Result<Receiver<ChainNotifyResponse>>;
+
     async fn ipc_get_prev_checkpoint_for_child(
         &self,
         child_subnet_id: SubnetID,
     ) -> Result<IPCGetPrevCheckpointForChildResponse>;
 
-    /// Returns the checkpoint template at `epoch`.
-    async fn ipc_get_checkpoint_template(&self, epoch: ChainEpoch) -> Result<Checkpoint>;
+    /// Returns the checkpoint template at `epoch`, read from the gateway actor at `tip_set`.
+    async fn ipc_get_checkpoint_template(
+        &self,
+        epoch: ChainEpoch,
+        tip_set: &TipSetKey,
+    ) -> Result<Checkpoint>;
 
     /// Returns the state of the gateway actor at `tip_set`.
-    async fn ipc_read_gateway_state(&self, tip_set: Cid) -> Result<IPCReadGatewayStateResponse>;
+    async fn ipc_read_gateway_state(
+        &self,
+        tip_set: &TipSetKey,
+    ) -> Result<IPCReadGatewayStateResponse>;
 
     /// Returns the state of the subnet actor at `tip_set`.
     async fn ipc_read_subnet_actor_state(
         &self,
         subnet_id: &SubnetID,
-        tip_set: Cid,
+        tip_set: &TipSetKey,
     ) -> Result<IPCReadSubnetActorStateResponse>;
 
+    /// Returns whether `validator` has already voted the bottom-up checkpoint of `subnet_id` at
+    /// `epoch` in the subnet actor deployed in the parent.
+    async fn ipc_has_voted_bottom_up_checkpoint(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validator: &Address,
+    ) -> Result<bool>;
+
+    /// Returns whether each of the `validators` has already voted the bottom-up checkpoint of
+    /// `subnet_id` at `epoch`, in the same order. By default, the validators are queried one after
+    /// the other, implementations can override it to query them at once.
+    async fn ipc_has_voted_bottom_up_checkpoints(
+        &self,
+        subnet_id: &SubnetID,
+        epoch: ChainEpoch,
+        validators: &[Address],
+    ) -> Result<Vec<bool>> {
+        let mut votes = Vec::with_capacity(validators.len());
+        for validator in validators {
+            votes.push(
+                self.ipc_has_voted_bottom_up_checkpoint(subnet_id, epoch, validator)
+                    .await?,
+            );
+        }
+        Ok(votes)
+    }
+
     /// Returns the list of subnets in a gateway.
     async fn ipc_list_child_subnets(&self, gateway_addr: Address) -> Result<Vec<SubnetInfo>>;
 }
+
+/// The generic helpers of the Lotus client api, which cannot be part of [`LotusClient`] without
+/// making it object-safe. It is implemented for every `LotusClient`, including `dyn LotusClient`.
+#[async_trait]
+pub trait LotusClientExt: LotusClient {
+    /// Read the state of the address at tipset decoded as `State`, see: https://lotus.filecoin.io/reference/lotus/state/#statereadstate
+    async fn read_state<State: DeserializeOwned + Debug>(
+        &self,
+        address: Address,
+        tip_set: &TipSetKey,
+    ) -> Result<ReadStateResponse<State>> {
+        let r = self.state_read_state(address, tip_set).await?;
+        Ok(ReadStateResponse {
+            balance: r.balance,
+            code: r.code,
+            state: serde_json::from_value(r.state)?,
+        })
+    }
+}
+
+impl<C: LotusClient + ?Sized> LotusClientExt for C {}
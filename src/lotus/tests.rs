@@ -1,9 +1,12 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
+use std::time::Duration;
+use tokio::net::TcpListener;
 use url::Url;
 
 use crate::jsonrpc::JsonRpcClientImpl;
 use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::mock::{MockChain, MockLotus};
 use crate::lotus::LotusClient;
 
 const HTTP_ENDPOINT: &str = "https://api.node.glif.io/rpc/v0";
@@ -49,3 +52,46 @@ async fn chain_head() {
     assert!(!head.blocks.is_empty());
     assert_eq!(head.cids.len(), head.blocks.len());
 }
+
+/// Starts a server that accepts connections but never answers, and returns its url with the
+/// `scheme`.
+async fn stalling_server(scheme: &str) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = vec![];
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+    Url::parse(&format!("{scheme}://{addr}/rpc/v0")).unwrap()
+}
+
+#[tokio::test]
+async fn from_subnet_falls_back_to_http() {
+    let lotus = MockLotus::start(MockChain::new("/root"));
+    let mut subnet = lotus.subnet(vec![]);
+    subnet.jsonrpc_api_ws = Some(stalling_server("ws").await);
+    subnet.request_timeout = Some(1);
+
+    let client = LotusJsonRPCClient::from_subnet(&subnet);
+    assert_eq!(client.state_network_name().await.unwrap(), "/root");
+    // The websocket endpoint is skipped while unhealthy.
+    assert_eq!(client.state_network_name().await.unwrap(), "/root");
+}
+
+#[tokio::test]
+async fn from_subnet_request_timeout() {
+    let lotus = MockLotus::start(MockChain::new("/root"));
+    let mut subnet = lotus.subnet(vec![]);
+    subnet.jsonrpc_api_http = stalling_server("http").await;
+    subnet.jsonrpc_api_ws = Some(stalling_server("ws").await);
+    subnet.request_timeout = Some(1);
+
+    // Both the websocket connection and the fallback http request time out.
+    let client = LotusJsonRPCClient::from_subnet(&subnet);
+    let response = tokio::time::timeout(Duration::from_secs(10), client.state_network_name())
+        .await
+        .expect("the request timeout did not fire");
+    assert!(response.is_err());
+}
@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: MIT
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use cid::Cid;
@@ -18,39 +20,84 @@ use fvm_shared::MethodNum;
 use ipc_gateway::Checkpoint;
 use ipc_sdk::subnet_id::SubnetID;
 use primitives::TCid;
+use serde::{Deserialize, Serialize};
 use tokio::select;
-use tokio::sync::Notify;
+use tokio::sync::{broadcast, watch};
 use tokio::time::sleep;
 use tokio_graceful_shutdown::SubsystemHandle;
 
 use crate::config::{ReloadableConfig, Subnet};
-use crate::jsonrpc::JsonRpcClient;
+use crate::jsonrpc::{JsonRpcError, JsonRpcErrorKind};
 use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::message::chain::TipSetKey;
+use crate::lotus::message::ipc::IPCReadSubnetActorStateResponse;
 use crate::lotus::message::mpool::MpoolPushMessage;
 use crate::lotus::LotusClient;
+use crate::manager::event::EVENT_CHANNEL_CAPACITY;
+use crate::manager::MessageExecutionError;
 
-/// The frequency at which to check a new chain head.
+/// The frequency at which to check a new chain head when the child subnet has no websocket
+/// endpoint to be notified of head changes.
 const CHAIN_HEAD_REQUEST_PERIOD: Duration = Duration::from_secs(10);
+/// The time to wait before restarting a subnet manager after its first failure. It is doubled
+/// after each consecutive failure, up to `MAX_RESTART_BACKOFF`.
+const INITIAL_RESTART_BACKOFF: Duration = Duration::from_secs(5);
+/// The maximum time to wait before restarting a failed subnet manager.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+
+/// The name of the file, next to the config, where the last checkpoint epoch processed for each
+/// subnet and the last one submitted on behalf of each account are persisted.
+pub const CHECKPOINT_STATE_FILE: &str = "checkpoints.json";
+
+/// The checkpointing progress of a subnet, as persisted in the checkpoint state file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedSubnet {
+    last_processed_epoch: Option<ChainEpoch>,
+    /// The last checkpoint epoch submitted on behalf of each account.
+    #[serde(default)]
+    last_submitted_epochs: HashMap<String, ChainEpoch>,
+}
+
+/// Creates the `LotusClient` used to interact with a subnet.
+pub type LotusClientFactory = Arc<dyn Fn(&Subnet) -> Arc<dyn LotusClient> + Send + Sync>;
 
 /// The `CheckpointSubsystem`. When run, it actively monitors subnets and submits checkpoints.
-struct CheckpointSubsystem {
+pub struct CheckpointSubsystem {
     /// The subsystem uses a `ReloadableConfig` to ensure that, at all, times, the subnets under
     /// management are those in the latest version of the config.
-    config: ReloadableConfig,
+    config: Arc<ReloadableConfig>,
+    /// The status of the managed subnets. It outlives the subnet managers so that it is kept
+    /// across their restarts, either on failure or on config reloads.
+    tracker: Arc<CheckpointTracker>,
+    /// Creates the clients of the managed subnets.
+    client_factory: LotusClientFactory,
 }
 
 impl CheckpointSubsystem {
-    /// Creates a new `CheckpointSubsystem` with a configuration `config`.
-    #[allow(dead_code)]
-    fn new(config: ReloadableConfig) -> Self {
-        Self { config }
+    /// Creates a new `CheckpointSubsystem` with a configuration `config`, which reports the status
+    /// of the managed subnets to `tracker`. The subnets are reached through their JSON-RPC
+    /// endpoints.
+    pub fn new(config: Arc<ReloadableConfig>, tracker: Arc<CheckpointTracker>) -> Self {
+        Self {
+            config,
+            tracker,
+            client_factory: Arc::new(|subnet: &Subnet| -> Arc<dyn LotusClient> {
+                Arc::new(LotusJsonRPCClient::from_subnet(subnet))
+            }),
+        }
+    }
+
+    /// Sets the factory of the clients used to interact with the managed subnets, replacing the
+    /// JSON-RPC clients created by default.
+    pub fn with_client_factory(mut self, client_factory: LotusClientFactory) -> Self {
+        self.client_factory = client_factory;
+        self
     }
 
     /// Runs the checkpoint subsystem, which actively monitors subnets and submits checkpoints.
     /// For each (account, subnet) that exists in the config, the subnet is monitored and checkpoints
     /// are submitted at the appropriate epochs.
-    #[allow(dead_code)]
-    async fn run(&self, subsys: SubsystemHandle) -> Result<()> {
+    pub async fn run(&self, subsys: SubsystemHandle) -> Result<()> {
         // Each event in this channel is notification of a new config.
         let mut config_chan = self.config.new_subscriber();
 
@@ -58,18 +105,22 @@ impl CheckpointSubsystem {
             // Load the latest config.
             let config = self.config.get_config();
 
-            // Create a `manage_subnet` future for each (child, parent) subnet pair under management
-            // and collect them in a `FuturesUnordered` set.
+            // Create a supervised `manage_subnet` future for each (child, parent) subnet pair under
+            // management and collect them in a `FuturesUnordered` set. Each of them is restarted
+            // independently on failure, so that a failing subnet does not stop the others.
             let manage_subnet_futures = FuturesUnordered::new();
-            let stop_subnet_managers = Arc::new(Notify::new());
+            let (stop_subnet_managers, stop_notify) = watch::channel(false);
             for (child, parent) in subnets_to_manage(&config.subnets) {
-                manage_subnet_futures
-                    .push(manage_subnet((child, parent), stop_subnet_managers.clone()));
+                manage_subnet_futures.push(supervise_subnet(
+                    (child, parent),
+                    self.client_factory.clone(),
+                    self.tracker.clone(),
+                    stop_notify.clone(),
+                ));
             }
 
             // Spawn a task to drive the `manage_subnet` futures.
-            let manage_subnets_task =
-                tokio::spawn(manage_subnet_futures.collect::<Vec<Result<()>>>());
+            let manage_subnets_task = tokio::spawn(manage_subnet_futures.collect::<Vec<()>>());
 
             // Watch for shutdown requests and config changes.
             let is_shutdown = select! {
@@ -82,15 +133,264 @@ impl CheckpointSubsystem {
                 },
             };
 
+            // In both cases, cleanly stop the `manage_subnet` futures. On a config change, they are
+            // recreated from the new config in the next iteration of the loop.
+            stop_subnet_managers.send_replace(true);
+            manage_subnets_task.await?;
+
             if is_shutdown {
-                // Cleanly stop the `manage_subnet` futures and return.
-                stop_subnet_managers.notify_waiters();
-                let results = manage_subnets_task.await?;
-                results.into_iter().collect::<Result<Vec<_>>>()?;
+                log::info!("checkpoint subsystem stopped");
                 return anyhow::Ok(());
             }
+            log::info!("config changed, restarting subnet managers");
+        }
+    }
+}
+
+/// The status of the checkpoint manager of a child subnet.
+#[derive(Debug, Clone, Default)]
+pub struct SubnetManagerStatus {
+    /// The number of times the manager was restarted after a failure.
+    pub restarts: u64,
+    /// The error that caused the last failure of the manager, if any.
+    pub last_error: Option<String>,
+    /// The checkpoint period read from the subnet actor in the parent.
+    pub check_period: Option<ChainEpoch>,
+    /// The last epoch seen at the head of the child subnet.
+    pub last_seen_epoch: Option<ChainEpoch>,
+    /// The last checkpoint epoch for which all the checkpoints were processed.
+    pub last_processed_epoch: Option<ChainEpoch>,
+    /// The status of each account of the child subnet.
+    pub accounts: HashMap<Address, AccountStatus>,
+}
+
+/// The checkpointing status of an account in a child subnet.
+#[derive(Debug, Clone, Default)]
+pub struct AccountStatus {
+    /// Whether the account was in the validator set the last time it was read.
+    pub in_validator_set: bool,
+    /// The epoch of the last checkpoint the account voted for.
+    pub last_submitted_epoch: Option<ChainEpoch>,
+    /// The CID of the message of the last checkpoint submitted by the agent on behalf of the
+    /// account. It is `None` if the vote was not cast by this agent.
+    pub last_submitted_cid: Option<Cid>,
+}
+
+/// The vote of an account in a checkpoint of a child subnet, as observed by the checkpoint
+/// subsystem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointEvent {
+    /// The child subnet of the checkpoint.
+    pub subnet: SubnetID,
+    pub account: Address,
+    pub epoch: ChainEpoch,
+    /// The CID of the message of the checkpoint submitted by the agent. It is `None` if the vote
+    /// was not cast by this agent.
+    pub message_cid: Option<Cid>,
+}
+
+/// Keeps track of the status of the checkpoint manager of each child subnet. Among others, it keeps
+/// the last checkpoint epoch processed, so that no checkpoint epoch is skipped, and the last
+/// checkpoint submitted for each account, so that checkpoints are not submitted twice when the same
+/// checkpoint epoch is observed more than once. Each recorded submission is also published as a
+/// [`CheckpointEvent`] to the subscribers of the tracker.
+///
+/// A tracker created with [`CheckpointTracker::new`] persists the last processed and submitted
+/// epochs, so that the checkpoint epochs missed while the agent is down are caught up on after a
+/// restart. A default tracker keeps them in memory only, in which case the checkpoint managers
+/// start again from the chain head after a restart.
+pub struct CheckpointTracker {
+    statuses: RwLock<HashMap<SubnetID, SubnetManagerStatus>>,
+    events: broadcast::Sender<CheckpointEvent>,
+    /// The file where the last processed and submitted epochs are persisted, if any.
+    state_path: Option<PathBuf>,
+}
+
+impl Default for CheckpointTracker {
+    fn default() -> Self {
+        Self {
+            statuses: RwLock::new(HashMap::new()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            state_path: None,
+        }
+    }
+}
+
+impl CheckpointTracker {
+    /// Creates a tracker that persists the last checkpoint epoch processed for each subnet and
+    /// the last one submitted on behalf of each account to `state_path`. The epochs already
+    /// persisted there, if any, are loaded.
+    pub fn new(state_path: PathBuf) -> Result<Self> {
+        let mut statuses: HashMap<SubnetID, SubnetManagerStatus> = HashMap::new();
+        if state_path.exists() {
+            let persisted: HashMap<String, PersistedSubnet> =
+                serde_json::from_slice(&std::fs::read(&state_path)?)?;
+            for (subnet, persisted) in persisted {
+                let status = statuses.entry(SubnetID::from_str(&subnet)?).or_default();
+                status.last_processed_epoch = persisted.last_processed_epoch;
+                for (account, epoch) in persisted.last_submitted_epochs {
+                    let account = Address::from_str(&account)?;
+                    status
+                        .accounts
+                        .entry(account)
+                        .or_default()
+                        .last_submitted_epoch = Some(epoch);
+                }
+            }
+        }
+
+        Ok(Self {
+            statuses: RwLock::new(statuses),
+            state_path: Some(state_path),
+            ..Default::default()
+        })
+    }
+
+    /// Subscribes to the checkpoints submitted from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<CheckpointEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns the status of every (child, parent) subnet pair to manage according to `subnets`.
+    /// Subnets whose manager has not been started yet have a default status.
+    pub fn managed_subnets(
+        &self,
+        subnets: &HashMap<String, Subnet>,
+    ) -> Vec<(Subnet, Subnet, SubnetManagerStatus)> {
+        let statuses = self.statuses.read().unwrap();
+        subnets_to_manage(subnets)
+            .into_iter()
+            .map(|(child, parent)| {
+                let status = statuses.get(&child.id).cloned().unwrap_or_default();
+                (child, parent, status)
+            })
+            .collect()
+    }
+
+    fn update<F: FnOnce(&mut SubnetManagerStatus)>(&self, subnet: &SubnetID, f: F) {
+        let mut statuses = self.statuses.write().unwrap();
+        f(statuses.entry(subnet.clone()).or_default());
+    }
+
+    /// Returns the last checkpoint epoch of `subnet` that was fully processed.
+    fn last_processed(&self, subnet: &SubnetID) -> Option<ChainEpoch> {
+        let statuses = self.statuses.read().unwrap();
+        statuses.get(subnet).and_then(|s| s.last_processed_epoch)
+    }
+
+    /// Records that all the checkpoints of `subnet` for `epoch` were processed.
+    fn record_processed(&self, subnet: &SubnetID, epoch: ChainEpoch) {
+        self.update(subnet, |s| s.last_processed_epoch = Some(epoch));
+        self.persist();
+    }
+
+    /// Records the latest chain head epoch of `subnet`.
+    fn record_seen(&self, subnet: &SubnetID, epoch: ChainEpoch) {
+        self.update(subnet, |s| s.last_seen_epoch = Some(epoch));
+    }
+
+    /// Records the checkpoint period of `subnet`.
+    fn record_check_period(&self, subnet: &SubnetID, period: ChainEpoch) {
+        self.update(subnet, |s| s.check_period = Some(period));
+    }
+
+    /// Records which of the `accounts` of `subnet` are in `validator_set`.
+    fn record_validators(
+        &self,
+        subnet: &SubnetID,
+        accounts: &[Address],
+        validator_set: &HashSet<Address>,
+    ) {
+        self.update(subnet, |s| {
+            for account in accounts {
+                s.accounts.entry(*account).or_default().in_validator_set =
+                    validator_set.contains(account);
+            }
+        });
+    }
+
+    /// Returns true if a checkpoint for `epoch` or a later one was already submitted on behalf of
+    /// `account` for `subnet`.
+    fn is_submitted(&self, subnet: &SubnetID, account: &Address, epoch: ChainEpoch) -> bool {
+        let statuses = self.statuses.read().unwrap();
+        statuses
+            .get(subnet)
+            .and_then(|s| s.accounts.get(account))
+            .and_then(|a| a.last_submitted_epoch)
+            .map_or(false, |last| last >= epoch)
+    }
+
+    /// Records that the checkpoint for `epoch` was submitted on behalf of `account` for `subnet`
+    /// with the message `cid`, if it was submitted by the agent.
+    fn record_submitted(
+        &self,
+        subnet: &SubnetID,
+        account: &Address,
+        epoch: ChainEpoch,
+        cid: Option<Cid>,
+    ) {
+        self.update(subnet, |s| {
+            let account = s.accounts.entry(*account).or_default();
+            account.last_submitted_epoch = Some(epoch);
+            account.last_submitted_cid = cid;
+        });
+        self.persist();
+        // Sending fails when there is no subscriber, in which case the event is just dropped.
+        let _ = self.events.send(CheckpointEvent {
+            subnet: subnet.clone(),
+            account: *account,
+            epoch,
+            message_cid: cid,
+        });
+    }
+
+    /// Persists the progress of every subnet, if the tracker has a state file. The checkpoints
+    /// were processed anyway, so failing to persist them does not fail the manager.
+    fn persist(&self) {
+        if let Some(path) = &self.state_path {
+            if let Err(e) = self.write_state(path) {
+                log::warn!("cannot persist checkpoint state to {path:?}: {e:}");
+            }
         }
     }
+
+    /// Writes the progress of every subnet to `path`. The file is replaced at once, so that it is
+    /// never left half written.
+    fn write_state(&self, path: &Path) -> Result<()> {
+        let persisted: HashMap<String, PersistedSubnet> = {
+            let statuses = self.statuses.read().unwrap();
+            statuses
+                .iter()
+                .map(|(subnet, status)| {
+                    let last_submitted_epochs = status
+                        .accounts
+                        .iter()
+                        .filter_map(|(account, a)| {
+                            a.last_submitted_epoch.map(|e| (account.to_string(), e))
+                        })
+                        .collect();
+                    let persisted = PersistedSubnet {
+                        last_processed_epoch: status.last_processed_epoch,
+                        last_submitted_epochs,
+                    };
+                    (subnet.to_string(), persisted)
+                })
+                .collect()
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&persisted)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Records a failure of the manager of `subnet`, which is about to be restarted.
+    fn record_failure(&self, subnet: &SubnetID, err: &anyhow::Error) {
+        self.update(subnet, |s| {
+            s.restarts += 1;
+            s.last_error = Some(err.to_string());
+        });
+    }
 }
 
 /// This function takes a `HashMap<String, Subnet>` and returns a `Vec` of tuples of the form
@@ -115,122 +415,625 @@ fn subnets_to_manage(subnets: &HashMap<String, Subnet>) -> Vec<(Subnet, Subnet)>
         .collect()
 }
 
-/// Monitors a subnet `child` for checkpoint blocks. It emits an event for every new checkpoint block.
-async fn manage_subnet((child, parent): (Subnet, Subnet), stop_notify: Arc<Notify>) -> Result<()> {
-    let child_client = LotusJsonRPCClient::from_subnet(&child);
-    let parent_client = LotusJsonRPCClient::from_subnet(&parent);
+/// Runs `manage_subnet` for the `child` subnet until a stop notification is received. Whenever
+/// `manage_subnet` fails, the error is recorded in the status of the subnet and it is restarted
+/// after an exponential backoff.
+async fn supervise_subnet(
+    (child, parent): (Subnet, Subnet),
+    client_factory: LotusClientFactory,
+    tracker: Arc<CheckpointTracker>,
+    mut stop_notify: watch::Receiver<bool>,
+) {
+    let mut backoff = INITIAL_RESTART_BACKOFF;
+
+    loop {
+        let started_at = Instant::now();
+        let result = manage_subnet(
+            (child.clone(), parent.clone()),
+            client_factory(&child),
+            client_factory(&parent),
+            tracker.clone(),
+            stop_notify.clone(),
+        )
+        .await;
+
+        let err = match result {
+            // The manager only returns successfully when it is asked to stop.
+            Ok(()) => return,
+            Err(e) => e,
+        };
+
+        // If the manager ran for long enough, this is not a consecutive failure, so we reset the
+        // backoff.
+        if started_at.elapsed() > MAX_RESTART_BACKOFF {
+            backoff = INITIAL_RESTART_BACKOFF;
+        }
 
-    // Read the parent's chain head and obtain the tip set CID.
+        log::error!(
+            "manager of subnet {} failed, restarting in {backoff:?}: {err:?}",
+            child.id
+        );
+        tracker.record_failure(&child.id, &err);
+
+        select! {
+            _ = sleep(backoff) => {}
+            _ = stop_notify.changed() => { return; }
+        }
+        backoff = std::cmp::min(backoff * 2, MAX_RESTART_BACKOFF);
+    }
+}
+
+/// Monitors a subnet `child` for checkpoint blocks. It emits an event for every new checkpoint block.
+/// `child_client` and `parent_client` are used to interact with the `child` and `parent` subnets.
+async fn manage_subnet(
+    (child, parent): (Subnet, Subnet),
+    child_client: Arc<dyn LotusClient>,
+    parent_client: Arc<dyn LotusClient>,
+    tracker: Arc<CheckpointTracker>,
+    mut stop_notify: watch::Receiver<bool>,
+) -> Result<()> {
+    // Read the parent's chain head and obtain the tip set key.
     let parent_head = parent_client.chain_head().await?;
-    let cid_map = parent_head.cids.first().unwrap().clone();
-    let parent_tip_set = Cid::try_from(cid_map)?;
+    let parent_tip_set = parent_head.tip_set_key()?;
 
     // Extract the checkpoint period from the state of the subnet actor in the parent.
     let state = parent_client
-        .ipc_read_subnet_actor_state(&child.id, parent_tip_set)
+        .ipc_read_subnet_actor_state(&child.id, &parent_tip_set)
         .await?;
     let period = state.check_period;
+    tracker.record_check_period(&child.id, period);
+    tracker.record_validators(&child.id, &child.accounts, &validator_set(state)?);
 
-    // We should have a way of knowing whether the validator has voted in the current open
-    // checkpoint epoch.
-    // TODO: Hook this up to the new IPC methods.
+    // If the child subnet has a websocket endpoint, we subscribe to its head changes so that we
+    // can react to them as soon as they happen. Otherwise, or if the subscription fails, we poll
+    // the chain head periodically.
+    let mut head_changes = match child.jsonrpc_api_ws {
+        Some(_) => match child_client.chain_notify().await {
+            Ok(head_changes) => Some(head_changes),
+            Err(e) => {
+                log::warn!(
+                    "cannot subscribe to the head changes of subnet {}, polling instead: {e:}",
+                    child.id
+                );
+                None
+            }
+        },
+        None => None,
+    };
 
-    // We can now start looping. In each loop we read the child subnet's chain head and check if
-    // it's a checkpoint epoch. If it is, we construct and submit a checkpoint.
+    // We can now start looping. In each loop we read the child subnet's chain head and walk every
+    // checkpoint epoch between the last one we processed and the head. For each of them, we
+    // construct and submit a checkpoint. This way, checkpoint epochs that fall between two reads of
+    // the chain head are not missed. Each checkpoint points to the previous one, so the walk only
+    // moves to the next epoch once the checkpoints of the current one are executed. As this can
+    // take a while, the walk returns as soon as a stop notification is received.
     loop {
         let child_head = child_client.chain_head().await?;
-        let epoch: ChainEpoch = ChainEpoch::try_from(child_head.height)?;
-        if epoch % period == 0 {
-            // It's a checkpointing epoch and we may have checkpoints to submit.
-
-            // First, we check which accounts are in the validator set. This is done by reading
-            // the parent's chain head and requesting the state at that tip set.
-            let parent_head = parent_client.chain_head().await?;
-            // A key assumption we make now is that each block has exactly one tip set. We panic
-            // if this is not the case as it violates our assumption.
-            // TODO: update this logic once the assumption changes (i.e., mainnet)
-            assert_eq!(parent_head.cids.len(), 1);
-            let cid_map = parent_head.cids.first().unwrap().clone();
-            let parent_tip_set = Cid::try_from(cid_map)?;
-
-            let subnet_actor_state = parent_client
-                .ipc_read_subnet_actor_state(&child.id, parent_tip_set)
-                .await?;
-
-            let mut validator_set: HashSet<Address, RandomState> = HashSet::new();
-            match subnet_actor_state.validator_set.validators {
-                None => {}
-                Some(validators) => {
-                    for v in validators {
-                        validator_set.insert(Address::from_str(v.addr.deref())?);
-                    }
+        let head_epoch: ChainEpoch = ChainEpoch::try_from(child_head.height)?;
+        tracker.record_seen(&child.id, head_epoch);
+
+        // If we have not processed any checkpoint epoch yet, we start from the latest checkpoint
+        // epoch that is not ahead of the chain head.
+        let mut epoch = match tracker.last_processed(&child.id) {
+            Some(last) => last + period,
+            None => head_epoch - head_epoch % period,
+        };
+        while epoch <= head_epoch {
+            // The checkpoint is built from the tip set at the checkpoint epoch, which might not
+            // be the chain head if we are catching up.
+            let child_tip_set = if epoch == head_epoch {
+                child_head.tip_set_key()?
+            } else {
+                let tip_set = child_client.chain_get_tipset_by_height(epoch);
+                match unless_stopped(&mut stop_notify, tip_set).await {
+                    Some(tip_set) => tip_set?.tip_set_key()?,
+                    None => return Ok(()),
                 }
             };
 
-            // Now, for each account defined in the `child` subnet that is in the validator set, we
-            // submit a checkpoint on its behalf.
-            assert_eq!(child_head.cids.len(), 1); // Again, check key assumption
-            let child_tip_set = Cid::try_from(child_head.cids.first().unwrap().clone())?;
-            for account in child.accounts.iter() {
-                if validator_set.contains(account) {
-                    submit_checkpoint(
-                        child_tip_set,
-                        epoch,
-                        account,
-                        &child,
-                        &child_client,
-                        &parent_client,
-                    )
-                    .await?;
-                }
+            let submitted = submit_epoch_checkpoints(
+                &child_tip_set,
+                epoch,
+                &child,
+                &tracker,
+                child_client.as_ref(),
+                parent_client.as_ref(),
+                &mut stop_notify,
+            )
+            .await?;
+            if !submitted {
+                return Ok(());
             }
+
+            tracker.record_processed(&child.id, epoch);
+            epoch += period;
         }
 
-        // Sleep for an appropriate amount of time before checking the chain head again or return
-        // if a stop notification is received.
-        select! {
-            _ = sleep(CHAIN_HEAD_REQUEST_PERIOD) => {}
-            _ = stop_notify.notified() => { return Ok(()); }
+        // Wait for the next head change, or for an appropriate amount of time if we are polling,
+        // before checking the chain head again. Return if a stop notification is received. If
+        // the subscription closes, we switch to polling.
+        match &head_changes {
+            Some(changes) => {
+                let closed = select! {
+                    r = changes.recv() => r.is_err(),
+                    _ = stop_notify.changed() => { return Ok(()); }
+                };
+                if closed {
+                    log::warn!(
+                        "head changes subscription of subnet {} closed, polling instead",
+                        child.id
+                    );
+                    head_changes = None;
+                } else {
+                    // All the pending changes are covered by the next read of the chain head.
+                    while changes.try_recv().is_ok() {}
+                }
+            }
+            None => {
+                select! {
+                    _ = sleep(CHAIN_HEAD_REQUEST_PERIOD) => {}
+                    _ = stop_notify.changed() => { return Ok(()); }
+                }
+            }
         }
     }
 }
 
-/// Submits a checkpoint for `epoch` on behalf of `account` to the subnet actor of `child_subnet`
-/// deployed on the parent subnet.
-async fn submit_checkpoint<T: JsonRpcClient + Send + Sync>(
-    child_tip_set: Cid,
+/// Submits the checkpoint for `epoch` on behalf of all the accounts of `child` that are in the
+/// validator set and have not voted in that checkpoint epoch yet, and waits for their execution.
+/// Returns false if a stop notification is received before all of them are executed.
+async fn submit_epoch_checkpoints(
+    child_tip_set: &TipSetKey,
     epoch: ChainEpoch,
-    account: &Address,
-    child_subnet: &Subnet,
-    child_client: &LotusJsonRPCClient<T>,
-    parent_client: &LotusJsonRPCClient<T>,
-) -> Result<()> {
-    let mut checkpoint = Checkpoint::new(child_subnet.id.clone(), epoch);
+    child: &Subnet,
+    tracker: &CheckpointTracker,
+    child_client: &dyn LotusClient,
+    parent_client: &dyn LotusClient,
+    stop_notify: &mut watch::Receiver<bool>,
+) -> Result<bool> {
+    // First, we check which accounts are in the validator set. This is done by reading
+    // the parent's chain head and requesting the state at that tip set.
+    let parent_head = parent_client.chain_head().await?;
+    let parent_tip_set = parent_head.tip_set_key()?;
+
+    let subnet_actor_state = parent_client
+        .ipc_read_subnet_actor_state(&child.id, &parent_tip_set)
+        .await?;
+    let validator_set = validator_set(subnet_actor_state)?;
+    tracker.record_validators(&child.id, &child.accounts, &validator_set);
+
+    // Now, we look for the accounts defined in the `child` subnet that are in the validator set
+    // and that did not vote in this checkpoint epoch yet.
+    let mut candidates = vec![];
+    for account in child.accounts.iter() {
+        if !validator_set.contains(account) {
+            continue;
+        }
+        // Skip the accounts whose checkpoint we already submitted.
+        if tracker.is_submitted(&child.id, account, epoch) {
+            log::debug!("checkpoint for epoch {epoch} already submitted for {account}");
+            continue;
+        }
+        candidates.push(*account);
+    }
+    if candidates.is_empty() {
+        return Ok(true);
+    }
+
+    // The checkpoint might have been submitted by other means, e.g. a previous run of the agent,
+    // so we check whether each candidate already voted.
+    let votes = parent_client
+        .ipc_has_voted_bottom_up_checkpoints(&child.id, epoch, &candidates)
+        .await?;
+
+    let mut accounts = vec![];
+    for (account, voted) in candidates.iter().zip(votes) {
+        if voted {
+            log::debug!("account {account} already voted checkpoint for epoch {epoch}");
+            tracker.record_submitted(&child.id, account, epoch, None);
+        } else {
+            accounts.push(account);
+        }
+    }
+    if accounts.is_empty() {
+        return Ok(true);
+    }
+
+    // The checkpoint is the same for all the accounts.
+    let mut checkpoint = Checkpoint::new(child.id.clone(), epoch);
 
     // Get the children checkpoints from the template on the gateway actor of the child subnet.
-    let template = child_client.ipc_get_checkpoint_template(epoch).await?;
+    let template = child_client
+        .ipc_get_checkpoint_template(epoch, child_tip_set)
+        .await?;
     checkpoint.data.children = template.data.children;
 
-    // Get the CID of previous checkpoint of the child subnet from the gateway actor of the parent
-    // subnet.
-    let response = parent_client
-        .ipc_get_prev_checkpoint_for_child(child_subnet.id.clone())
+    let prev_checkpoint = parent_client
+        .ipc_get_prev_checkpoint_for_child(child.id.clone())
         .await?;
-    let cid = Cid::try_from(response.cid)?;
+    let cid = Cid::try_from(prev_checkpoint.cid)?;
     checkpoint.data.prev_check = TCid::from(cid);
+    // The proof is made of the whole tip set key, as the tip set may contain more than one block.
     checkpoint.data.proof = child_tip_set.to_bytes();
 
-    // The checkpoint is constructed. Now we call the `submit_checkpoint` method on the subnet actor
-    // of the child subnet that is deployed on the parent subnet.
+    for account in accounts {
+        let cid = submit_checkpoint(&checkpoint, account, child, parent_client).await?;
+        log::info!("submitted checkpoint for epoch {epoch} on behalf of {account} with cid {cid}");
+
+        // The previous checkpoint of the next epoch is only up to date once this one is executed.
+        // If it fails, the epoch is not recorded as processed, so it is submitted again when the
+        // manager restarts, or when the agent restarts with a persisted tracker.
+        let response = match unless_stopped(stop_notify, parent_client.state_wait_msg(cid)).await {
+            Some(response) => response?,
+            None => return Ok(false),
+        };
+        if let Some(err) = MessageExecutionError::from_receipt(&response.receipt) {
+            return Err(anyhow::Error::from(err).context(format!(
+                "checkpoint for epoch {epoch} on behalf of {account} failed"
+            )));
+        }
+        tracker.record_submitted(&child.id, account, epoch, Some(cid));
+    }
+
+    Ok(true)
+}
+
+/// Runs `future` to completion, unless a stop notification is received first, in which case
+/// `None` is returned.
+async fn unless_stopped<F: Future>(
+    stop_notify: &mut watch::Receiver<bool>,
+    future: F,
+) -> Option<F::Output> {
+    select! {
+        r = future => Some(r),
+        _ = stop_notify.changed() => None,
+    }
+}
+
+/// Returns the addresses of the validators in the state of a subnet actor.
+fn validator_set(state: IPCReadSubnetActorStateResponse) -> Result<HashSet<Address>> {
+    let mut validator_set: HashSet<Address, RandomState> = HashSet::new();
+    match state.validator_set.validators {
+        None => {}
+        Some(validators) => {
+            for v in validators {
+                validator_set.insert(Address::from_str(v.addr.deref())?);
+            }
+        }
+    };
+    Ok(validator_set)
+}
+
+/// Submits `checkpoint` on behalf of `account` to the subnet actor of `child_subnet` deployed on
+/// the parent subnet. Returns the CID of the submitted message.
+async fn submit_checkpoint(
+    checkpoint: &Checkpoint,
+    account: &Address,
+    child_subnet: &Subnet,
+    parent_client: &dyn LotusClient,
+) -> Result<Cid> {
+    // We call the `submit_checkpoint` method on the subnet actor of the child subnet that is
+    // deployed on the parent subnet.
     let to = child_subnet.id.subnet_actor();
     let from = *account;
     let message = MpoolPushMessage::new(
         to,
         from,
         ipc_subnet_actor::Method::SubmitCheckpoint as MethodNum,
-        cbor::serialize(&checkpoint, "checkpoint")?.to_vec(),
+        cbor::serialize(checkpoint, "checkpoint")?.to_vec(),
     );
-    parent_client.mpool_push_message(message).await?;
+    let response = match parent_client.mpool_push_message(message.clone()).await {
+        // The nonce is assigned by the node, so a nonce too low means that another message of
+        // the same account was pushed concurrently. Pushing again gets a fresh nonce.
+        Err(e) if JsonRpcError::kind_of(&e) == Some(JsonRpcErrorKind::NonceTooLow) => {
+            log::warn!("nonce too low submitting checkpoint for {account}, retrying");
+            parent_client.mpool_push_message(message).await?
+        }
+        r => r?,
+    };
+
+    response.cid()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use fil_actors_runtime::cbor;
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared::address::Address;
+    use fvm_shared::MethodNum;
+    use ipc_gateway::Checkpoint;
+    use ipc_sdk::subnet_id::SubnetID;
+    use tokio::sync::{broadcast, watch};
+    use tokio::time::sleep;
+
+    use crate::lotus::client::LotusJsonRPCClient;
+    use crate::lotus::message::chain::TipSetKey;
+    use crate::lotus::mock::{checkpoint_cid, mock_cid, MockChain, MockLotus, MockSubnetActor};
+    use crate::manager::checkpoint::{manage_subnet, CheckpointTracker};
+
+    const ROOT_ID: &str = "/root";
+    const CHILD_ID: &str = "/root/f0100";
+
+    #[tokio::test]
+    async fn submit_missed_checkpoints() {
+        let account = Address::new_id(100);
+        let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+
+        let mut parent_chain = MockChain::new(ROOT_ID);
+        parent_chain.height = 100;
+        parent_chain.subnet_actors.insert(
+            child_id.clone(),
+            MockSubnetActor {
+                check_period: 10,
+                validators: vec![account],
+            },
+        );
+        // The account already voted in the checkpoint of epoch 10.
+        parent_chain.votes.insert((child_id.clone(), 10, account));
+        let parent = MockLotus::start(parent_chain);
+
+        let mut child_chain = MockChain::new(CHILD_ID);
+        child_chain.height = 35;
+        child_chain.blocks_per_tip_set = 2;
+        let child = MockLotus::start(child_chain);
+
+        // Checkpoint epochs 10, 20 and 30 were missed since epoch 0.
+        let tracker = Arc::new(CheckpointTracker::default());
+        tracker.record_processed(&child_id, 0);
 
-    Ok(())
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(manage_subnet(
+            (child.subnet(vec![account]), parent.subnet(vec![])),
+            Arc::new(child.client()),
+            Arc::new(parent.client()),
+            tracker.clone(),
+            stop_rx,
+        ));
+
+        for _ in 0..100 {
+            if tracker.last_processed(&child_id) == Some(30) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        stop_tx.send_replace(true);
+        task.await.unwrap().unwrap();
+        assert_eq!(tracker.last_processed(&child_id), Some(30));
+
+        // Only the checkpoints of epochs 20 and 30 were submitted.
+        let parent_chain = parent.chain();
+        assert_eq!(parent_chain.mpool.len(), 2);
+        for message in parent_chain.mpool.iter() {
+            assert_eq!(message.to, child_id.subnet_actor());
+            assert_eq!(message.from, account);
+            assert_eq!(
+                message.method,
+                ipc_subnet_actor::Method::SubmitCheckpoint as MethodNum
+            );
+        }
+        let checkpoints = parent_chain
+            .mpool
+            .iter()
+            .map(|m| {
+                cbor::deserialize::<Checkpoint>(&RawBytes::new(m.params.clone()), "checkpoint")
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // The proof is the key of the whole tip set at the checkpoint epoch.
+        let tip_set = TipSetKey::new(child.chain().tip_set_cids(20)).unwrap();
+        assert_eq!(checkpoints[0].data.proof, tip_set.to_bytes());
+
+        // Each checkpoint points to the one submitted before it, which was executed first.
+        assert_eq!(
+            checkpoints[0].data.prev_check.cid(),
+            mock_cid("genesis checkpoint")
+        );
+        assert_eq!(
+            checkpoints[1].data.prev_check.cid(),
+            checkpoint_cid(&parent_chain.mpool[0].cid)
+        );
+        let message = &parent_chain.mpool[1];
+
+        let status = tracker
+            .statuses
+            .read()
+            .unwrap()
+            .get(&child_id)
+            .cloned()
+            .unwrap();
+        assert_eq!(status.check_period, Some(10));
+        assert_eq!(status.last_seen_epoch, Some(35));
+        let account_status = &status.accounts[&account];
+        assert!(account_status.in_validator_set);
+        assert_eq!(account_status.last_submitted_epoch, Some(30));
+        assert_eq!(account_status.last_submitted_cid, Some(message.cid));
+    }
+
+    #[test]
+    fn persist_submitted_epochs() {
+        let account = Address::new_id(100);
+        let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("checkpoints.json");
+
+        let tracker = CheckpointTracker::new(state_path.clone()).unwrap();
+        assert!(!tracker.is_submitted(&child_id, &account, 10));
+        tracker.record_submitted(&child_id, &account, 10, None);
+
+        // The submissions are loaded again by a new tracker, e.g. after a restart.
+        let tracker = CheckpointTracker::new(state_path).unwrap();
+        assert!(tracker.is_submitted(&child_id, &account, 10));
+        assert!(!tracker.is_submitted(&child_id, &account, 20));
+    }
+
+    #[tokio::test]
+    async fn catch_up_after_restart() {
+        let account = Address::new_id(100);
+        let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("checkpoints.json");
+
+        // The agent processed the checkpoint epoch 10 before it stopped.
+        let tracker = CheckpointTracker::new(state_path.clone()).unwrap();
+        tracker.record_processed(&child_id, 10);
+        drop(tracker);
+
+        let mut parent_chain = MockChain::new(ROOT_ID);
+        parent_chain.height = 100;
+        parent_chain.subnet_actors.insert(
+            child_id.clone(),
+            MockSubnetActor {
+                check_period: 10,
+                validators: vec![account],
+            },
+        );
+        let parent = MockLotus::start(parent_chain);
+        let mut child_chain = MockChain::new(CHILD_ID);
+        child_chain.height = 35;
+        let child = MockLotus::start(child_chain);
+
+        // After the restart, the epochs 20 and 30 missed while the agent was down are submitted.
+        let tracker = Arc::new(CheckpointTracker::new(state_path.clone()).unwrap());
+        assert_eq!(tracker.last_processed(&child_id), Some(10));
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(manage_subnet(
+            (child.subnet(vec![account]), parent.subnet(vec![])),
+            Arc::new(child.client()),
+            Arc::new(parent.client()),
+            tracker.clone(),
+            stop_rx,
+        ));
+        for _ in 0..100 {
+            if tracker.last_processed(&child_id) == Some(30) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        stop_tx.send_replace(true);
+        task.await.unwrap().unwrap();
+
+        assert_eq!(parent.chain().mpool.len(), 2);
+        let tracker = CheckpointTracker::new(state_path).unwrap();
+        assert_eq!(tracker.last_processed(&child_id), Some(30));
+    }
+
+    #[tokio::test]
+    async fn submit_checkpoints_on_head_changes() {
+        let account = Address::new_id(100);
+        let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+
+        let mut parent_chain = MockChain::new(ROOT_ID);
+        parent_chain.height = 100;
+        parent_chain.subnet_actors.insert(
+            child_id.clone(),
+            MockSubnetActor {
+                check_period: 10,
+                validators: vec![account],
+            },
+        );
+        let parent = MockLotus::start(parent_chain);
+        let mut child_chain = MockChain::new(CHILD_ID);
+        child_chain.height = 15;
+        let child = MockLotus::start(child_chain);
+
+        // The child is followed through its websocket endpoint.
+        let mut child_subnet = child.subnet(vec![account]);
+        child_subnet.jsonrpc_api_ws = Some(child.ws_url());
+        let tracker = Arc::new(CheckpointTracker::default());
+        tracker.record_processed(&child_id, 10);
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(manage_subnet(
+            (child_subnet.clone(), parent.subnet(vec![])),
+            Arc::new(LotusJsonRPCClient::from_subnet(&child_subnet)),
+            Arc::new(parent.client()),
+            tracker.clone(),
+            stop_rx,
+        ));
+        let last_seen = || {
+            let statuses = tracker.statuses.read().unwrap();
+            statuses.get(&child_id).and_then(|s| s.last_seen_epoch)
+        };
+        for _ in 0..100 {
+            if last_seen() == Some(15) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(last_seen(), Some(15));
+
+        // The checkpoint of epoch 20 is submitted as soon as the head change is notified, well
+        // before the chain head would be polled again.
+        child.chain().height = 25;
+        child.notify_head_change();
+        for _ in 0..100 {
+            if tracker.last_processed(&child_id) == Some(20) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        stop_tx.send_replace(true);
+        task.await.unwrap().unwrap();
+
+        assert_eq!(tracker.last_processed(&child_id), Some(20));
+        assert_eq!(last_seen(), Some(25));
+        assert_eq!(parent.chain().mpool.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_when_chain_notify_fails() {
+        let account = Address::new_id(100);
+        let child_id = SubnetID::from_str(CHILD_ID).unwrap();
+
+        let mut parent_chain = MockChain::new(ROOT_ID);
+        parent_chain.height = 100;
+        parent_chain.subnet_actors.insert(
+            child_id.clone(),
+            MockSubnetActor {
+                check_period: 10,
+                validators: vec![account],
+            },
+        );
+        let parent = MockLotus::start(parent_chain);
+        let mut child_chain = MockChain::new(CHILD_ID);
+        child_chain.height = 35;
+        let child = MockLotus::start(child_chain);
+
+        // Nothing listens on the websocket endpoint, so the requests fall back to http and the
+        // manager polls the chain head.
+        let unused_addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut child_subnet = child.subnet(vec![account]);
+        child_subnet.jsonrpc_api_ws = Some(format!("ws://{unused_addr}").parse().unwrap());
+        let tracker = Arc::new(CheckpointTracker::default());
+        tracker.record_processed(&child_id, 0);
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(manage_subnet(
+            (child_subnet.clone(), parent.subnet(vec![])),
+            Arc::new(LotusJsonRPCClient::from_subnet(&child_subnet)),
+            Arc::new(parent.client()),
+            tracker.clone(),
+            stop_rx,
+        ));
+        for _ in 0..100 {
+            if tracker.last_processed(&child_id) == Some(30) {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        stop_tx.send_replace(true);
+        task.await.unwrap().unwrap();
+
+        assert_eq!(tracker.last_processed(&child_id), Some(30));
+        assert_eq!(parent.chain().mpool.len(), 3);
+    }
 }
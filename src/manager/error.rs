@@ -0,0 +1,159 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The errors of the execution of the messages sent by a subnet manager
+
+use base64::Engine;
+use fil_actors_runtime::cbor;
+use fvm_ipld_encoding::RawBytes;
+use serde::{Deserialize, Serialize};
+
+use crate::jsonrpc::JsonRpcError;
+use crate::lotus::message::state::Receipt;
+
+/// The error of a message that was included in the chain but whose execution failed, i.e. its
+/// receipt has a non-zero exit code. It is returned wrapped in an [`anyhow::Error`], from which it
+/// can be recovered with `downcast_ref::<MessageExecutionError>()`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("message execution failed with exit code {exit_code}: {message} (gas used: {gas_used})")]
+pub struct MessageExecutionError {
+    pub exit_code: u32,
+    /// The description of the exit code, followed by the error returned by the actor, if any.
+    pub message: String,
+    pub gas_used: u64,
+}
+
+impl MessageExecutionError {
+    /// Returns the error of the execution of the message with `receipt`, or `None` if the
+    /// execution succeeded.
+    pub fn from_receipt(receipt: &Receipt) -> Option<Self> {
+        let exit_code = receipt.exit_code();
+        if exit_code == 0 {
+            return None;
+        }
+
+        let description = exit_code_description(exit_code);
+        let message = match returned_error(&receipt.result) {
+            Some(error) => format!("{description}: {error}"),
+            None => description.to_string(),
+        };
+
+        Some(Self {
+            exit_code,
+            message,
+            gas_used: receipt.gas_used(),
+        })
+    }
+
+    /// Returns the `MessageExecutionError` carried in the data of the `JsonRpcError` wrapped in
+    /// `error`, if any. This is how the error is recovered on the client side of the agent RPC.
+    pub fn from_rpc_error(error: &anyhow::Error) -> Option<Self> {
+        let data = error.downcast_ref::<JsonRpcError>()?.data.clone()?;
+        serde_json::from_value(data).ok()
+    }
+}
+
+/// Returns the description of the exit codes defined by the FVM and the builtin actors, see:
+/// https://github.com/filecoin-project/ref-fvm/blob/master/shared/src/error/mod.rs
+fn exit_code_description(exit_code: u32) -> &'static str {
+    match exit_code {
+        1 => "invalid sender",
+        2 => "invalid sender state",
+        4 => "illegal instruction",
+        5 => "invalid receiver",
+        6 => "insufficient funds to transfer the value",
+        7 => "out of gas",
+        9 => "illegal exit code",
+        10 => "assertion failed in the system",
+        11 => "missing return value",
+        16 => "illegal argument",
+        17 => "not found",
+        18 => "forbidden",
+        19 => "insufficient funds",
+        20 => "illegal state",
+        21 => "serialization error",
+        22 => "unhandled message",
+        23 => "unspecified error",
+        24 => "assertion failed in the actor",
+        _ => "actor error",
+    }
+}
+
+/// Decodes the error returned by the actor in the base64 encoded `result` of a receipt. Actors
+/// return the error either as a cbor string or as raw text, if at all.
+fn returned_error(result: &str) -> Option<String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(result)
+        .ok()?;
+    if bytes.is_empty() {
+        return None;
+    }
+
+    cbor::deserialize::<String>(&RawBytes::new(bytes.clone()), "actor error")
+        .ok()
+        .or_else(|| String::from_utf8(bytes).ok())
+        .filter(|error| !error.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use base64::Engine;
+    use fil_actors_runtime::cbor;
+
+    use crate::jsonrpc::JsonRpcError;
+    use crate::lotus::message::state::Receipt;
+    use crate::manager::MessageExecutionError;
+
+    fn receipt(exit_code: u32, result: &[u8]) -> Receipt {
+        serde_json::from_value(serde_json::json!({
+            "ExitCode": exit_code,
+            "Return": base64::engine::general_purpose::STANDARD.encode(result),
+            "GasUsed": 1000,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn successful_receipt() {
+        assert_eq!(MessageExecutionError::from_receipt(&receipt(0, &[])), None);
+    }
+
+    #[test]
+    fn failed_receipt() {
+        let error = MessageExecutionError::from_receipt(&receipt(19, &[])).unwrap();
+        assert_eq!(
+            error,
+            MessageExecutionError {
+                exit_code: 19,
+                message: String::from("insufficient funds"),
+                gas_used: 1000,
+            }
+        );
+
+        let result = cbor::serialize(&"collateral too low", "error").unwrap();
+        let error = MessageExecutionError::from_receipt(&receipt(16, &result.to_vec())).unwrap();
+        assert_eq!(error.message, "illegal argument: collateral too low");
+    }
+
+    #[test]
+    fn from_rpc_error() {
+        let error = MessageExecutionError {
+            exit_code: 16,
+            message: String::from("illegal argument"),
+            gas_used: 1000,
+        };
+        let rpc_error = anyhow!(JsonRpcError {
+            code: -32000,
+            message: error.to_string(),
+            data: Some(serde_json::to_value(&error).unwrap()),
+        });
+        assert_eq!(
+            MessageExecutionError::from_rpc_error(&rpc_error),
+            Some(error)
+        );
+        assert_eq!(
+            MessageExecutionError::from_rpc_error(&anyhow!("other")),
+            None
+        );
+    }
+}
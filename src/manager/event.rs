@@ -0,0 +1,44 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The events of the lifecycle of the subnets managed by the agent
+
+use cid::Cid;
+use fvm_shared::address::Address;
+use ipc_sdk::subnet_id::SubnetID;
+use tokio::sync::broadcast;
+
+/// The number of events buffered for each subscriber. A subscriber that lags behind by more than
+/// this number of events misses the oldest ones.
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// An event of the lifecycle of a subnet, caused by a message executed on behalf of the agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubnetEvent {
+    pub kind: SubnetEventKind,
+    /// The subnet of the event. It is the parent subnet for the creation of a subnet, as the
+    /// created subnet is only known by the address of its actor.
+    pub subnet: SubnetID,
+    /// The account that sent the message of the event.
+    pub from: Address,
+    /// The cid of the message of the event.
+    pub message_cid: Cid,
+}
+
+/// The kind of a [`SubnetEvent`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubnetEventKind {
+    /// A subnet actor was created with the address `actor`.
+    Created {
+        actor: Address,
+    },
+    Joined,
+    Left,
+    Killed,
+    Funded,
+    Released,
+}
+
+/// Creates the channel to which the subnet managers publish their [`SubnetEvent`]s.
+pub fn subnet_event_channel() -> broadcast::Sender<SubnetEvent> {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
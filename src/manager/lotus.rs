@@ -1,19 +1,21 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-use crate::config::Subnet;
+use crate::config::{Subnet, DEFAULT_IPC_GATEWAY_ADDR};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use cid::Cid;
 use fil_actors_runtime::types::{InitExecParams, InitExecReturn, INIT_EXEC_METHOD_NUM};
 use fil_actors_runtime::{builtin::singletons::INIT_ACTOR_ADDR, cbor};
 use fvm_shared::{address::Address, econ::TokenAmount, MethodNum};
-use ipc_gateway::Checkpoint;
+use ipc_gateway::{Checkpoint, FundParams, PropagateParams, WhitelistPropagatorParams};
 use ipc_sdk::subnet_id::SubnetID;
 use ipc_subnet_actor::{types::MANIFEST_ID, ConstructParams, JoinParams};
+use tokio::sync::{broadcast, oneshot};
 
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
+use crate::jsonrpc::{JsonRpcClientImpl, JsonRpcError, JsonRpcErrorKind};
 use crate::lotus::client::LotusJsonRPCClient;
 use crate::lotus::message::ipc::SubnetInfo;
 use crate::lotus::message::mpool::MpoolPushMessage;
@@ -21,14 +23,27 @@ use crate::lotus::message::state::StateWaitMsgResponse;
 use crate::lotus::LotusClient;
 
 use super::subnet::SubnetManager;
-
-pub struct LotusSubnetManager<T: JsonRpcClient> {
-    lotus_client: LotusJsonRPCClient<T>,
+use super::{MessageExecutionError, MessageTracker, SubnetEvent, SubnetEventKind};
+
+/// The `SubnetManager` that interacts with a Lotus node through any `LotusClient`
+/// implementation.
+pub struct LotusSubnetManager<T: LotusClient> {
+    lotus_client: T,
+    /// Records the status of the messages sent by the manager, if set.
+    message_tracker: Option<Arc<MessageTracker>>,
+    /// Notified of the cid of the next message pushed to the mpool, if set.
+    on_submitted: Mutex<Option<oneshot::Sender<Cid>>>,
+    /// Receives the events of the subnets caused by the messages of the manager, if set.
+    events: Option<broadcast::Sender<SubnetEvent>>,
 }
 
 #[async_trait]
-impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
-    async fn create_subnet(&self, from: Address, params: ConstructParams) -> Result<Address> {
+impl<T: LotusClient> SubnetManager for LotusSubnetManager<T> {
+    async fn create_subnet(
+        &self,
+        from: Address,
+        params: ConstructParams,
+    ) -> Result<(Address, StateWaitMsgResponse)> {
         if !self.is_network_match(&params.parent).await? {
             return Err(anyhow!("subnet actor being deployed in the wrong parent network, parent network names do not match"));
         }
@@ -52,8 +67,14 @@ impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
             .parse_result_into::<InitExecReturn>()?;
         let addr = result.id_address;
         log::info!("created subnet result: {addr:}");
+        self.publish_event(
+            SubnetEventKind::Created { actor: addr },
+            params.parent,
+            from,
+            &state_wait_response,
+        )?;
 
-        Ok(addr)
+        Ok((addr, state_wait_response))
     }
 
     async fn join_subnet(
@@ -62,7 +83,7 @@ impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
         from: Address,
         collateral: TokenAmount,
         params: JoinParams,
-    ) -> Result<()> {
+    ) -> Result<StateWaitMsgResponse> {
         let parent = subnet.parent().ok_or_else(|| anyhow!("cannot join root"))?;
         if !self.is_network_match(&parent).await? {
             return Err(anyhow!("subnet actor being deployed in the wrong parent network, parent network names do not match"));
@@ -77,13 +98,14 @@ impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
         );
         message.value = collateral;
 
-        self.mpool_push_and_wait(message).await?;
+        let state_wait_response = self.mpool_push_and_wait(message).await?;
         log::info!("joined subnet: {subnet:}");
+        self.publish_event(SubnetEventKind::Joined, subnet, from, &state_wait_response)?;
 
-        Ok(())
+        Ok(state_wait_response)
     }
 
-    async fn leave_subnet(&self, subnet: SubnetID, from: Address) -> Result<()> {
+    async fn leave_subnet(&self, subnet: SubnetID, from: Address) -> Result<StateWaitMsgResponse> {
         let parent = subnet
             .parent()
             .ok_or_else(|| anyhow!("cannot leave root"))?;
@@ -91,34 +113,38 @@ impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
             return Err(anyhow!("subnet actor being deployed in the wrong parent network, parent network names do not match"));
         }
 
-        self.mpool_push_and_wait(MpoolPushMessage::new(
-            subnet.subnet_actor(),
-            from,
-            ipc_subnet_actor::Method::Leave as MethodNum,
-            vec![],
-        ))
-        .await?;
+        let state_wait_response = self
+            .mpool_push_and_wait(MpoolPushMessage::new(
+                subnet.subnet_actor(),
+                from,
+                ipc_subnet_actor::Method::Leave as MethodNum,
+                vec![],
+            ))
+            .await?;
         log::info!("left subnet: {subnet:}");
+        self.publish_event(SubnetEventKind::Left, subnet, from, &state_wait_response)?;
 
-        Ok(())
+        Ok(state_wait_response)
     }
 
-    async fn kill_subnet(&self, subnet: SubnetID, from: Address) -> Result<()> {
+    async fn kill_subnet(&self, subnet: SubnetID, from: Address) -> Result<StateWaitMsgResponse> {
         let parent = subnet.parent().ok_or_else(|| anyhow!("cannot kill root"))?;
         if !self.is_network_match(&parent).await? {
             return Err(anyhow!("subnet actor being deployed in the wrong parent network, parent network names do not match"));
         }
 
-        self.mpool_push_and_wait(MpoolPushMessage::new(
-            subnet.subnet_actor(),
-            from,
-            ipc_subnet_actor::Method::Kill as MethodNum,
-            vec![],
-        ))
-        .await?;
-        log::info!("left subnet: {subnet:}");
+        let state_wait_response = self
+            .mpool_push_and_wait(MpoolPushMessage::new(
+                subnet.subnet_actor(),
+                from,
+                ipc_subnet_actor::Method::Kill as MethodNum,
+                vec![],
+            ))
+            .await?;
+        log::info!("killed subnet: {subnet:}");
+        self.publish_event(SubnetEventKind::Killed, subnet, from, &state_wait_response)?;
 
-        Ok(())
+        Ok(state_wait_response)
     }
 
     async fn submit_checkpoint(
@@ -146,47 +172,234 @@ impl<T: JsonRpcClient + Send + Sync> SubnetManager for LotusSubnetManager<T> {
         Ok(map)
     }
 
-    async fn fund(&self, _subnet: SubnetID, _from: Address, _amount: TokenAmount) -> Result<()> {
-        todo!()
+    async fn fund(
+        &self,
+        subnet: SubnetID,
+        from: Address,
+        amount: TokenAmount,
+    ) -> Result<StateWaitMsgResponse> {
+        let parent = subnet.parent().ok_or_else(|| anyhow!("cannot fund root"))?;
+        if !self.is_network_match(&parent).await? {
+            return Err(anyhow!("fund message being sent to the wrong parent network, parent network names do not match"));
+        }
+
+        let params = FundParams {
+            value: subnet.clone(),
+        };
+        let mut message = MpoolPushMessage::new(
+            Address::new_id(DEFAULT_IPC_GATEWAY_ADDR),
+            from,
+            ipc_gateway::Method::Fund as MethodNum,
+            cbor::serialize(&params, "fund subnet params")?.to_vec(),
+        );
+        message.value = amount;
+
+        let state_wait_response = self.mpool_push_and_wait(message).await?;
+        log::info!(
+            "funded subnet: {subnet:} with exit code: {:}",
+            state_wait_response.exit_code()
+        );
+        self.publish_event(SubnetEventKind::Funded, subnet, from, &state_wait_response)?;
+
+        Ok(state_wait_response)
     }
 
-    async fn release(&self, _subnet: SubnetID, _from: Address) -> Result<()> {
-        todo!()
+    async fn release(
+        &self,
+        subnet: SubnetID,
+        from: Address,
+        amount: TokenAmount,
+    ) -> Result<StateWaitMsgResponse> {
+        // Release is triggered from the child subnet, so we check that we are talking to the
+        // child instead of the parent.
+        if !self.is_network_match(&subnet).await? {
+            return Err(anyhow!(
+                "release message being sent to the wrong subnet, network names do not match"
+            ));
+        }
+
+        let mut message = MpoolPushMessage::new(
+            Address::new_id(DEFAULT_IPC_GATEWAY_ADDR),
+            from,
+            ipc_gateway::Method::Release as MethodNum,
+            vec![],
+        );
+        message.value = amount;
+
+        let state_wait_response = self.mpool_push_and_wait(message).await?;
+        log::info!(
+            "released funds from subnet: {subnet:} with exit code: {:}",
+            state_wait_response.exit_code()
+        );
+        self.publish_event(
+            SubnetEventKind::Released,
+            subnet,
+            from,
+            &state_wait_response,
+        )?;
+
+        Ok(state_wait_response)
     }
 
     async fn propagate(
         &self,
-        _subnet: SubnetID,
-        _from: Address,
-        _postbox_msg_cid: Cid,
-    ) -> Result<()> {
-        todo!()
+        subnet: SubnetID,
+        from: Address,
+        postbox_msg_cid: Cid,
+    ) -> Result<StateWaitMsgResponse> {
+        // The postbox lives in the gateway of `subnet`, check that we are talking to it.
+        if !self.is_network_match(&subnet).await? {
+            return Err(anyhow!(
+                "propagate message being sent to the wrong subnet, network names do not match"
+            ));
+        }
+
+        let params = PropagateParams {
+            postbox_cid: postbox_msg_cid,
+        };
+        let state_wait_response = self
+            .mpool_push_and_wait(MpoolPushMessage::new(
+                Address::new_id(DEFAULT_IPC_GATEWAY_ADDR),
+                from,
+                ipc_gateway::Method::Propagate as MethodNum,
+                cbor::serialize(&params, "propagate params")?.to_vec(),
+            ))
+            .await?;
+        log::info!("propagated postbox message: {postbox_msg_cid:} in subnet: {subnet:}");
+
+        Ok(state_wait_response)
     }
 
     async fn whitelist_propagator(
         &self,
-        _subnet: SubnetID,
-        _postbox_msg_cid: Cid,
-        _from: Address,
-        _to_add: Vec<Address>,
-    ) -> Result<()> {
-        todo!()
+        subnet: SubnetID,
+        postbox_msg_cid: Cid,
+        from: Address,
+        to_add: Vec<Address>,
+    ) -> Result<StateWaitMsgResponse> {
+        if !self.is_network_match(&subnet).await? {
+            return Err(anyhow!("whitelist propagator message being sent to the wrong subnet, network names do not match"));
+        }
+
+        let params = WhitelistPropagatorParams {
+            postbox_cid: postbox_msg_cid,
+            to_add,
+        };
+        let state_wait_response = self
+            .mpool_push_and_wait(MpoolPushMessage::new(
+                Address::new_id(DEFAULT_IPC_GATEWAY_ADDR),
+                from,
+                ipc_gateway::Method::WhitelistPropagator as MethodNum,
+                cbor::serialize(&params, "whitelist propagator params")?.to_vec(),
+            ))
+            .await?;
+        log::info!(
+            "whitelisted propagators for postbox message: {postbox_msg_cid:} in subnet: {subnet:}"
+        );
+
+        Ok(state_wait_response)
     }
 }
 
-impl<T: JsonRpcClient + Send + Sync> LotusSubnetManager<T> {
-    pub fn new(lotus_client: LotusJsonRPCClient<T>) -> Self {
-        Self { lotus_client }
+impl<T: LotusClient> LotusSubnetManager<T> {
+    pub fn new(lotus_client: T) -> Self {
+        Self {
+            lotus_client,
+            message_tracker: None,
+            on_submitted: Mutex::new(None),
+            events: None,
+        }
+    }
+
+    /// Publishes the events of the subnets caused by the messages of the manager to `events`.
+    pub fn with_event_sender(mut self, events: broadcast::Sender<SubnetEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Records the status of the messages sent by the manager in `message_tracker`.
+    pub fn with_message_tracker(mut self, message_tracker: Arc<MessageTracker>) -> Self {
+        self.message_tracker = Some(message_tracker);
+        self
     }
 
-    /// Publish the message to memory pool and wait for the response
+    /// Sends the cid of the next message pushed to the mpool to `on_submitted`, before waiting for
+    /// its execution. This allows the caller to return as soon as the message is submitted, while
+    /// the manager keeps waiting in the background. If the manager fails before pushing a
+    /// message, `on_submitted` is dropped.
+    pub fn notify_submitted(self, on_submitted: oneshot::Sender<Cid>) -> Self {
+        *self.on_submitted.lock().unwrap() = Some(on_submitted);
+        self
+    }
+
+    /// Publish the message to memory pool and wait for the response. Returns a
+    /// `MessageExecutionError` if the message was executed with a non-zero exit code.
     async fn mpool_push_and_wait(&self, message: MpoolPushMessage) -> Result<StateWaitMsgResponse> {
-        let mem_push_response = self.lotus_client.mpool_push_message(message).await?;
+        let from = message.from;
+        let mem_push_response = match self.lotus_client.mpool_push_message(message).await {
+            Ok(response) => response,
+            Err(e) => {
+                return match JsonRpcError::kind_of(&e) {
+                    Some(JsonRpcErrorKind::InsufficientFunds) => {
+                        Err(e.context(format!("account {from} cannot pay for the message")))
+                    }
+                    Some(JsonRpcErrorKind::ActorNotFound) => Err(e.context(format!(
+                        "account {from} or the target of the message does not exist"
+                    ))),
+                    _ => Err(e),
+                };
+            }
+        };
 
         let message_cid = mem_push_response.cid()?;
         log::debug!("message published with cid: {message_cid:?}");
 
-        self.lotus_client.state_wait_msg(message_cid).await
+        if let Some(tracker) = &self.message_tracker {
+            tracker.record_pending(message_cid);
+        }
+        if let Some(on_submitted) = self.on_submitted.lock().unwrap().take() {
+            // The receiver may have stopped listening, which does not affect the message.
+            let _ = on_submitted.send(message_cid);
+        }
+
+        let result = self.wait_message(message_cid).await;
+        if let Some(tracker) = &self.message_tracker {
+            tracker.record_result(&message_cid, &result);
+        }
+        result
+    }
+
+    /// Waits for the execution of the message `message_cid` and checks that it succeeded.
+    async fn wait_message(&self, message_cid: Cid) -> Result<StateWaitMsgResponse> {
+        let state_wait_response = self.lotus_client.state_wait_msg(message_cid).await?;
+        // The message was included in the chain, but its execution might have failed.
+        if let Some(err) = MessageExecutionError::from_receipt(&state_wait_response.receipt) {
+            log::error!("message {message_cid} failed: {err}");
+            return Err(err.into());
+        }
+
+        Ok(state_wait_response)
+    }
+
+    /// Publishes the event of `kind` for `subnet`, caused by the message of `from` whose execution
+    /// is `state_wait_response`.
+    fn publish_event(
+        &self,
+        kind: SubnetEventKind,
+        subnet: SubnetID,
+        from: Address,
+        state_wait_response: &StateWaitMsgResponse,
+    ) -> Result<()> {
+        if let Some(events) = &self.events {
+            // Sending fails when there is no subscriber, in which case the event is just dropped.
+            let _ = events.send(SubnetEvent {
+                kind,
+                subnet,
+                from,
+                message_cid: state_wait_response.message_cid()?,
+            });
+        }
+        Ok(())
     }
 
     /// Checks the `network` is the one we are currently talking to.
@@ -217,7 +430,7 @@ impl<T: JsonRpcClient + Send + Sync> LotusSubnetManager<T> {
     }
 }
 
-impl LotusSubnetManager<JsonRpcClientImpl> {
+impl LotusSubnetManager<LotusJsonRPCClient<JsonRpcClientImpl>> {
     pub fn from_subnet(subnet: &Subnet) -> Self {
         let client = LotusJsonRPCClient::from_subnet(subnet);
         LotusSubnetManager::new(client)
@@ -0,0 +1,123 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Tracks the messages submitted by the subnet managers
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+use cid::Cid;
+
+use crate::lotus::message::state::StateWaitMsgResponse;
+
+/// The maximum number of messages kept by a `MessageTracker`. The oldest messages are forgotten
+/// first once it is reached.
+const MAX_TRACKED_MESSAGES: usize = 1024;
+
+/// The status of a message submitted by a subnet manager.
+#[derive(Debug, Clone)]
+pub enum MessageStatus {
+    /// The message was pushed to the mpool, but its execution was not observed yet.
+    Pending,
+    /// The message was executed successfully.
+    Executed(StateWaitMsgResponse),
+    /// The execution of the message failed, or it could not be waited for.
+    Failed(String),
+}
+
+/// Keeps, in memory, the status of the latest messages submitted by the subnet managers, so that
+/// the messages submitted without waiting for their execution can be followed up.
+#[derive(Default)]
+pub struct MessageTracker {
+    messages: RwLock<TrackedMessages>,
+}
+
+#[derive(Default)]
+struct TrackedMessages {
+    statuses: HashMap<Cid, MessageStatus>,
+    /// The cids of the messages in submission order.
+    order: VecDeque<Cid>,
+}
+
+impl MessageTracker {
+    /// Returns the status of the message `cid`, if it is tracked.
+    pub fn status(&self, cid: &Cid) -> Option<MessageStatus> {
+        let messages = self.messages.read().unwrap();
+        messages.statuses.get(cid).cloned()
+    }
+
+    /// Returns the status of all the tracked messages, in submission order.
+    pub fn statuses(&self) -> Vec<(Cid, MessageStatus)> {
+        let messages = self.messages.read().unwrap();
+        messages
+            .order
+            .iter()
+            .map(|cid| (*cid, messages.statuses[cid].clone()))
+            .collect()
+    }
+
+    /// Records that the message `cid` was pushed to the mpool.
+    pub(crate) fn record_pending(&self, cid: Cid) {
+        let mut messages = self.messages.write().unwrap();
+        if messages
+            .statuses
+            .insert(cid, MessageStatus::Pending)
+            .is_none()
+        {
+            messages.order.push_back(cid);
+        }
+        while messages.order.len() > MAX_TRACKED_MESSAGES {
+            if let Some(oldest) = messages.order.pop_front() {
+                messages.statuses.remove(&oldest);
+            }
+        }
+    }
+
+    /// Records the outcome of the message `cid`, if it is still tracked.
+    pub(crate) fn record_result(&self, cid: &Cid, result: &anyhow::Result<StateWaitMsgResponse>) {
+        let status = match result {
+            Ok(response) => MessageStatus::Executed(response.clone()),
+            Err(e) => MessageStatus::Failed(e.to_string()),
+        };
+        let mut messages = self.messages.write().unwrap();
+        if let Some(s) = messages.statuses.get_mut(cid) {
+            *s = status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::lotus::mock::mock_cid;
+    use crate::manager::message::{MessageStatus, MessageTracker, MAX_TRACKED_MESSAGES};
+
+    #[test]
+    fn track_messages() {
+        let tracker = MessageTracker::default();
+        let cid = mock_cid("message");
+        assert!(tracker.status(&cid).is_none());
+
+        tracker.record_pending(cid);
+        assert!(matches!(tracker.status(&cid), Some(MessageStatus::Pending)));
+
+        tracker.record_result(&cid, &Err(anyhow!("reverted")));
+        match tracker.status(&cid) {
+            Some(MessageStatus::Failed(e)) => assert_eq!(e, "reverted"),
+            s => panic!("unexpected status: {s:?}"),
+        }
+    }
+
+    #[test]
+    fn forget_oldest_messages() {
+        let tracker = MessageTracker::default();
+        for i in 0..=MAX_TRACKED_MESSAGES {
+            tracker.record_pending(mock_cid(&i.to_string()));
+        }
+
+        let statuses = tracker.statuses();
+        assert_eq!(statuses.len(), MAX_TRACKED_MESSAGES);
+        assert_eq!(statuses[0].0, mock_cid("1"));
+        assert!(tracker.status(&mock_cid("0")).is_none());
+    }
+}
@@ -1,10 +1,22 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
+pub use checkpoint::{
+    AccountStatus, CheckpointEvent, CheckpointSubsystem, CheckpointTracker, LotusClientFactory,
+    SubnetManagerStatus, CHECKPOINT_STATE_FILE,
+};
+pub use error::MessageExecutionError;
+pub use event::{subnet_event_channel, SubnetEvent, SubnetEventKind};
 pub use lotus::LotusSubnetManager;
+pub use message::{MessageStatus, MessageTracker};
 pub use subnet::SubnetManager;
 
 pub use crate::lotus::message::ipc::SubnetInfo;
 
-pub(crate) mod checkpoint;
+mod checkpoint;
+mod error;
+mod event;
 mod lotus;
+mod message;
 mod subnet;
+#[cfg(test)]
+mod tests;
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 
 use crate::lotus::message::ipc::SubnetInfo;
+use crate::lotus::message::state::StateWaitMsgResponse;
 ///! IPC node-specific traits.
 use anyhow::Result;
 use async_trait::async_trait;
@@ -18,26 +19,31 @@ pub trait SubnetManager {
     /// Deploys a new subnet actor on the `parent` subnet and with the
     /// configuration passed in `ConstructParams`.
     /// The result of the function is the ID address for the subnet actor from which the final
-    /// subet ID can be inferred.
-    async fn create_subnet(&self, from: Address, params: ConstructParams) -> Result<Address>;
+    /// subet ID can be inferred, along with the response of the executed creation message.
+    async fn create_subnet(
+        &self,
+        from: Address,
+        params: ConstructParams,
+    ) -> Result<(Address, StateWaitMsgResponse)>;
 
     /// Performs the call to join a subnet from a wallet address and staking an amount
     /// of collateral. This function, as well as all of the ones on this trait, can infer
     /// the specific subnet and actors on which to perform the relevant calls from the
-    /// SubnetID given as an argument.
+    /// SubnetID given as an argument. Returns the response of the executed join message.
     async fn join_subnet(
         &self,
         subnet: SubnetID,
         from: Address,
         collateral: TokenAmount,
         params: JoinParams,
-    ) -> Result<()>;
+    ) -> Result<StateWaitMsgResponse>;
 
-    /// Sends a request to leave a subnet from a wallet address.
-    async fn leave_subnet(&self, subnet: SubnetID, from: Address) -> Result<()>;
+    /// Sends a request to leave a subnet from a wallet address. Returns the response of the
+    /// executed leave message.
+    async fn leave_subnet(&self, subnet: SubnetID, from: Address) -> Result<StateWaitMsgResponse>;
 
-    /// Sends a signal to kill a subnet
-    async fn kill_subnet(&self, subnet: SubnetID, from: Address) -> Result<()>;
+    /// Sends a signal to kill a subnet. Returns the response of the executed kill message.
+    async fn kill_subnet(&self, subnet: SubnetID, from: Address) -> Result<StateWaitMsgResponse>;
 
     /// Submits a checkpoint for a subnet from a wallet address.
     async fn submit_checkpoint(
@@ -53,26 +59,42 @@ pub trait SubnetManager {
         gateway_addr: Address,
     ) -> Result<HashMap<SubnetID, SubnetInfo>>;
 
-    /// Fund injects new funds from an account of the parent chain to a subnet
-    async fn fund(&self, subnet: SubnetID, from: Address, amount: TokenAmount) -> Result<()>;
+    /// Fund injects new funds from an account of the parent chain to a subnet. Returns the
+    /// response of the executed fund message, from which its CID and exit code can be read.
+    async fn fund(
+        &self,
+        subnet: SubnetID,
+        from: Address,
+        amount: TokenAmount,
+    ) -> Result<StateWaitMsgResponse>;
 
-    /// Release creates a new check message to release funds in parent chain
-    async fn release(&self, subnet: SubnetID, from: Address) -> Result<()>;
+    /// Release creates a new check message to release funds in parent chain. The call is sent
+    /// to the gateway of the child `subnet`, and the returned response holds the CID of the
+    /// release message, which can be used to track the withdrawal until it is included in a
+    /// checkpoint of the parent.
+    async fn release(
+        &self,
+        subnet: SubnetID,
+        from: Address,
+        amount: TokenAmount,
+    ) -> Result<StateWaitMsgResponse>;
 
-    /// Propagate a cross-net message forward
+    /// Propagate a cross-net message forward. Returns the response of the executed propagate
+    /// message.
     async fn propagate(
         &self,
         subnet: SubnetID,
         from: Address,
         postbox_msg_cid: Cid,
-    ) -> anyhow::Result<()>;
+    ) -> Result<StateWaitMsgResponse>;
 
-    /// Whitelist a series of addresses as propagator of a cross net message
+    /// Whitelist a series of addresses as propagator of a cross net message. Returns the
+    /// response of the executed whitelist message.
     async fn whitelist_propagator(
         &self,
         subnet: SubnetID,
         postbox_msg_cid: Cid,
         from: Address,
         to_add: Vec<Address>,
-    ) -> anyhow::Result<()>;
+    ) -> Result<StateWaitMsgResponse>;
 }
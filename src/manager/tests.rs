@@ -0,0 +1,305 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+use std::str::FromStr;
+use std::sync::Arc;
+
+use fil_actors_runtime::builtin::singletons::INIT_ACTOR_ADDR;
+use fil_actors_runtime::cbor;
+use fil_actors_runtime::types::{InitExecReturn, INIT_EXEC_METHOD_NUM};
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::MethodNum;
+use ipc_gateway::{PropagateParams, WhitelistPropagatorParams};
+use ipc_sdk::subnet_id::SubnetID;
+use ipc_subnet_actor::{ConsensusType, ConstructParams, JoinParams};
+use tokio::sync::oneshot;
+
+use crate::config::DEFAULT_IPC_GATEWAY_ADDR;
+use crate::jsonrpc::JsonRpcClientImpl;
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::mock::{mock_cid, MockChain, MockLotus, MockReceipt};
+use crate::manager::{
+    LotusSubnetManager, MessageExecutionError, MessageStatus, MessageTracker, SubnetManager,
+};
+
+const ROOT_ID: &str = "/root";
+const CHILD_ID: &str = "/root/f0100";
+
+fn manager(lotus: &MockLotus) -> LotusSubnetManager<LotusJsonRPCClient<JsonRpcClientImpl>> {
+    LotusSubnetManager::new(lotus.client())
+}
+
+/// The params of a subnet created in the root network.
+fn construct_params() -> ConstructParams {
+    ConstructParams {
+        parent: SubnetID::from_str(ROOT_ID).unwrap(),
+        name: String::from("test"),
+        ipc_gateway_addr: DEFAULT_IPC_GATEWAY_ADDR,
+        consensus: ConsensusType::Mir,
+        min_validator_stake: TokenAmount::from_atto(10),
+        min_validators: 1,
+        finality_threshold: 2,
+        check_period: 10,
+        genesis: vec![],
+    }
+}
+
+#[tokio::test]
+async fn create_subnet() {
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+    let created = Address::new_id(1002);
+    let result = InitExecReturn {
+        id_address: created,
+        robust_address: created,
+    };
+    lotus.chain().next_receipts.push_back(MockReceipt {
+        return_data: cbor::serialize(&result, "init exec return")
+            .unwrap()
+            .to_vec(),
+        ..Default::default()
+    });
+
+    let from = Address::new_id(100);
+    let (addr, response) = manager(&lotus)
+        .create_subnet(from, construct_params())
+        .await
+        .unwrap();
+    assert_eq!(addr, created);
+
+    let chain = lotus.chain();
+    assert_eq!(chain.mpool.len(), 1);
+    assert_eq!(response.message_cid().unwrap(), chain.mpool[0].cid);
+    assert_eq!(response.height(), chain.height as u64);
+    assert_eq!(
+        response.tip_set().unwrap(),
+        chain.tip_set_cids(chain.height)
+    );
+    assert_eq!(chain.mpool[0].to, INIT_ACTOR_ADDR);
+    assert_eq!(chain.mpool[0].from, from);
+    assert_eq!(chain.mpool[0].method, INIT_EXEC_METHOD_NUM);
+}
+
+#[tokio::test]
+async fn create_subnet_wrong_parent() {
+    let lotus = MockLotus::start(MockChain::new(CHILD_ID));
+    let result = manager(&lotus)
+        .create_subnet(Address::new_id(100), construct_params())
+        .await;
+    assert!(result.is_err());
+    assert!(lotus.chain().mpool.is_empty());
+}
+
+#[tokio::test]
+async fn join_leave_kill_subnet() {
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+    let manager = manager(&lotus);
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let from = Address::new_id(100);
+
+    let params = JoinParams {
+        validator_net_addr: String::from("/ip4/127.0.0.1/tcp/1347"),
+    };
+    manager
+        .join_subnet(subnet.clone(), from, TokenAmount::from_atto(10), params)
+        .await
+        .unwrap();
+    manager.leave_subnet(subnet.clone(), from).await.unwrap();
+    manager.kill_subnet(subnet.clone(), from).await.unwrap();
+
+    let chain = lotus.chain();
+    let methods = chain
+        .mpool
+        .iter()
+        .map(|m| {
+            assert_eq!(m.to, subnet.subnet_actor());
+            assert_eq!(m.from, from);
+            m.method
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        vec![
+            ipc_subnet_actor::Method::Join as MethodNum,
+            ipc_subnet_actor::Method::Leave as MethodNum,
+            ipc_subnet_actor::Method::Kill as MethodNum,
+        ]
+    );
+    assert_eq!(chain.mpool[0].value, TokenAmount::from_atto(10));
+}
+
+#[tokio::test]
+async fn fund_subnet() {
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let response = manager(&lotus)
+        .fund(subnet, Address::new_id(100), TokenAmount::from_atto(5))
+        .await
+        .unwrap();
+    assert_eq!(response.exit_code(), 0);
+
+    let chain = lotus.chain();
+    assert_eq!(response.message_cid().unwrap(), chain.mpool[0].cid);
+    assert_eq!(chain.mpool[0].to, Address::new_id(DEFAULT_IPC_GATEWAY_ADDR));
+    assert_eq!(chain.mpool[0].value, TokenAmount::from_atto(5));
+}
+
+#[tokio::test]
+async fn release_funds() {
+    // Funds are released from the child subnet, through its gateway.
+    let lotus = MockLotus::start(MockChain::new(CHILD_ID));
+
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let response = manager(&lotus)
+        .release(subnet, Address::new_id(100), TokenAmount::from_atto(5))
+        .await
+        .unwrap();
+    assert_eq!(response.exit_code(), 0);
+
+    let chain = lotus.chain();
+    assert_eq!(response.message_cid().unwrap(), chain.mpool[0].cid);
+    assert_eq!(chain.mpool[0].to, Address::new_id(DEFAULT_IPC_GATEWAY_ADDR));
+    assert_eq!(
+        chain.mpool[0].method,
+        ipc_gateway::Method::Release as MethodNum
+    );
+    assert_eq!(chain.mpool[0].value, TokenAmount::from_atto(5));
+}
+
+#[tokio::test]
+async fn release_funds_wrong_subnet() {
+    // The parent network is rejected, as the release must be sent to the child.
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let result = manager(&lotus)
+        .release(subnet, Address::new_id(100), TokenAmount::from_atto(5))
+        .await;
+    assert!(result.is_err());
+    assert!(lotus.chain().mpool.is_empty());
+}
+
+#[tokio::test]
+async fn propagate_and_whitelist_propagator() {
+    let lotus = MockLotus::start(MockChain::new(CHILD_ID));
+    let manager = manager(&lotus);
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let from = Address::new_id(100);
+    let postbox_msg_cid = mock_cid("postbox message");
+    let to_add = vec![Address::new_id(101), Address::new_id(102)];
+
+    let whitelisted = manager
+        .whitelist_propagator(subnet.clone(), postbox_msg_cid, from, to_add.clone())
+        .await
+        .unwrap();
+    let propagated = manager
+        .propagate(subnet, from, postbox_msg_cid)
+        .await
+        .unwrap();
+
+    let chain = lotus.chain();
+    assert_eq!(chain.mpool.len(), 2);
+    assert_eq!(whitelisted.message_cid().unwrap(), chain.mpool[0].cid);
+    assert_eq!(propagated.message_cid().unwrap(), chain.mpool[1].cid);
+    for message in chain.mpool.iter() {
+        assert_eq!(message.to, Address::new_id(DEFAULT_IPC_GATEWAY_ADDR));
+        assert_eq!(message.from, from);
+    }
+
+    assert_eq!(
+        chain.mpool[0].method,
+        ipc_gateway::Method::WhitelistPropagator as MethodNum
+    );
+    let params =
+        fvm_ipld_encoding::from_slice::<WhitelistPropagatorParams>(&chain.mpool[0].params).unwrap();
+    assert_eq!(params.postbox_cid, postbox_msg_cid);
+    assert_eq!(params.to_add, to_add);
+
+    assert_eq!(
+        chain.mpool[1].method,
+        ipc_gateway::Method::Propagate as MethodNum
+    );
+    let params = fvm_ipld_encoding::from_slice::<PropagateParams>(&chain.mpool[1].params).unwrap();
+    assert_eq!(params.postbox_cid, postbox_msg_cid);
+}
+
+#[tokio::test]
+async fn propagate_wrong_subnet() {
+    // The postbox lives in the child subnet, so the messages are rejected by its parent.
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+    let manager = manager(&lotus);
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let from = Address::new_id(100);
+    let postbox_msg_cid = mock_cid("postbox message");
+
+    let result = manager
+        .propagate(subnet.clone(), from, postbox_msg_cid)
+        .await;
+    assert!(result.is_err());
+    let result = manager
+        .whitelist_propagator(subnet, postbox_msg_cid, from, vec![Address::new_id(101)])
+        .await;
+    assert!(result.is_err());
+    assert!(lotus.chain().mpool.is_empty());
+}
+
+#[tokio::test]
+async fn join_subnet_reverted() {
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+    lotus.chain().next_receipts.push_back(MockReceipt {
+        exit_code: 16,
+        return_data: cbor::serialize(&"collateral too low", "error")
+            .unwrap()
+            .to_vec(),
+        gas_used: 1234,
+    });
+
+    let params = JoinParams {
+        validator_net_addr: String::from("/ip4/127.0.0.1/tcp/1347"),
+    };
+    let err = manager(&lotus)
+        .join_subnet(
+            SubnetID::from_str(CHILD_ID).unwrap(),
+            Address::new_id(100),
+            TokenAmount::from_atto(1),
+            params,
+        )
+        .await
+        .unwrap_err();
+
+    let err = err.downcast_ref::<MessageExecutionError>().unwrap();
+    assert_eq!(err.exit_code, 16);
+    assert_eq!(err.message, "illegal argument: collateral too low");
+    assert_eq!(err.gas_used, 1234);
+}
+
+#[tokio::test]
+async fn track_submitted_messages() {
+    let lotus = MockLotus::start(MockChain::new(ROOT_ID));
+    lotus.chain().next_receipts.push_back(MockReceipt {
+        exit_code: 18,
+        ..Default::default()
+    });
+
+    let tracker = Arc::new(MessageTracker::default());
+    let (on_submitted, submitted) = oneshot::channel();
+    let manager = manager(&lotus)
+        .with_message_tracker(tracker.clone())
+        .notify_submitted(on_submitted);
+    let subnet = SubnetID::from_str(CHILD_ID).unwrap();
+    let from = Address::new_id(100);
+
+    // The first message fails, the second one is executed.
+    assert!(manager.leave_subnet(subnet.clone(), from).await.is_err());
+    manager.kill_subnet(subnet, from).await.unwrap();
+
+    let chain = lotus.chain();
+    assert_eq!(submitted.await.unwrap(), chain.mpool[0].cid);
+
+    let statuses = tracker.statuses();
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].0, chain.mpool[0].cid);
+    assert!(matches!(statuses[0].1, MessageStatus::Failed(_)));
+    assert_eq!(statuses[1].0, chain.mpool[1].cid);
+    assert!(matches!(statuses[1].1, MessageStatus::Executed(_)));
+}
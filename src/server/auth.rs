@@ -0,0 +1,107 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The authentication of the requests to the json rpc server, with bearer tokens granting the
+//! permissions configured in the `[server.auth]` section of the config.
+
+use crate::config::{json_rpc_methods, Permission, ServerAuth};
+use std::sync::Arc;
+use warp::reject::Reject;
+use warp::{Filter, Rejection};
+
+/// Returns the auth config the requests are checked against, so that it follows the reloads of
+/// the config. No authentication is required if it returns `None`.
+pub(crate) type AuthConfig = Arc<dyn Fn() -> Option<ServerAuth> + Send + Sync>;
+
+/// The rejection of the requests without a valid bearer token.
+#[derive(Debug)]
+pub(crate) struct Unauthorized;
+
+impl Reject for Unauthorized {}
+
+/// Extracts the permission granted to the bearer token of the request. The requests are granted
+/// every permission when the server has no auth config, and rejected with [`Unauthorized`] when
+/// their token is missing or unknown.
+pub(crate) fn with_permission(
+    auth: AuthConfig,
+) -> impl Filter<Extract = (Permission,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(
+        move |authorization: Option<String>| {
+            let current = auth();
+            async move {
+                match current {
+                    None => Ok(Permission::Admin),
+                    Some(auth) => authorization
+                        .as_deref()
+                        .and_then(bearer_token)
+                        .and_then(|token| auth.permission(token))
+                        .ok_or_else(|| warp::reject::custom(Unauthorized)),
+                }
+            }
+        },
+    )
+}
+
+/// Returns the permission required to call `method`.
+pub(crate) fn required_permission(method: &str) -> Permission {
+    match method {
+        json_rpc_methods::RELOAD_CONFIG => Permission::Admin,
+        json_rpc_methods::CREATE_SUBNET
+        | json_rpc_methods::JOIN_SUBNET
+        | json_rpc_methods::LEAVE_SUBNET
+        | json_rpc_methods::KILL_SUBNET
+        | json_rpc_methods::FUND_SUBNET
+        | json_rpc_methods::RELEASE_FUNDS
+        | json_rpc_methods::PROPAGATE
+        | json_rpc_methods::WHITELIST_PROPAGATOR => Permission::Sign,
+        json_rpc_methods::SUBSCRIBE_CHECKPOINTS
+        | json_rpc_methods::SUBSCRIBE_SUBNET_EVENTS
+        | json_rpc_methods::UNSUBSCRIBE => Permission::Write,
+        _ => Permission::Read,
+    }
+}
+
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(token.trim_start())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{json_rpc_methods, Permission};
+    use crate::server::auth::{bearer_token, required_permission};
+
+    #[test]
+    fn parse_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer  abc"), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("abc"), None);
+    }
+
+    #[test]
+    fn method_permissions() {
+        assert_eq!(
+            required_permission(json_rpc_methods::RELOAD_CONFIG),
+            Permission::Admin
+        );
+        assert_eq!(
+            required_permission(json_rpc_methods::JOIN_SUBNET),
+            Permission::Sign
+        );
+        assert_eq!(
+            required_permission(json_rpc_methods::SUBSCRIBE_CHECKPOINTS),
+            Permission::Write
+        );
+        assert_eq!(
+            required_permission(json_rpc_methods::LIST_CHILD_SUBNETS),
+            Permission::Read
+        );
+        assert!(Permission::Admin > Permission::Sign);
+        assert!(Permission::Sign > Permission::Write);
+        assert!(Permission::Write > Permission::Read);
+    }
+}
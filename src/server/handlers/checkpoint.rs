@@ -0,0 +1,112 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Expose the status of the checkpoint subsystem
+
+use crate::config::ReloadableConfig;
+use crate::manager::CheckpointTracker;
+use crate::server::JsonRPCRequestHandler;
+use async_trait::async_trait;
+use fvm_shared::clock::ChainEpoch;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CheckpointStatusParams {
+    /// Only return the status of this child subnet, optional
+    pub subnet: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CheckpointStatusResponse {
+    /// The status of every managed (child, parent) subnet pair
+    pub subnets: Vec<SubnetCheckpointStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SubnetCheckpointStatus {
+    pub child: String,
+    pub parent: String,
+    /// The checkpoint period of the child, if it was already read from the parent
+    pub check_period: Option<ChainEpoch>,
+    /// The last epoch seen at the head of the child
+    pub last_seen_epoch: Option<ChainEpoch>,
+    /// The last checkpoint epoch fully processed
+    pub last_processed_epoch: Option<ChainEpoch>,
+    /// The number of restarts of the subnet manager after failures
+    pub restarts: u64,
+    /// The error that caused the last failure of the subnet manager
+    pub last_error: Option<String>,
+    pub accounts: Vec<AccountCheckpointStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AccountCheckpointStatus {
+    pub address: String,
+    pub in_validator_set: bool,
+    pub last_submitted_epoch: Option<ChainEpoch>,
+    pub last_submitted_cid: Option<String>,
+}
+
+/// The checkpoint status json rpc method handler.
+pub(crate) struct CheckpointStatusHandler {
+    config: Arc<ReloadableConfig>,
+    tracker: Arc<CheckpointTracker>,
+}
+
+impl CheckpointStatusHandler {
+    pub(crate) fn new(config: Arc<ReloadableConfig>, tracker: Arc<CheckpointTracker>) -> Self {
+        Self { config, tracker }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for CheckpointStatusHandler {
+    type Request = CheckpointStatusParams;
+    type Response = CheckpointStatusResponse;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let config = self.config.get_config();
+
+        let subnets = self
+            .tracker
+            .managed_subnets(&config.subnets)
+            .into_iter()
+            .filter(|(child, _, _)| match &request.subnet {
+                Some(subnet) => child.id.to_string() == *subnet,
+                None => true,
+            })
+            .map(|(child, parent, status)| {
+                let accounts = child
+                    .accounts
+                    .iter()
+                    .map(|account| {
+                        let account_status = status.accounts.get(account).cloned();
+                        let account_status = account_status.unwrap_or_default();
+                        AccountCheckpointStatus {
+                            address: account.to_string(),
+                            in_validator_set: account_status.in_validator_set,
+                            last_submitted_epoch: account_status.last_submitted_epoch,
+                            last_submitted_cid: account_status
+                                .last_submitted_cid
+                                .map(|cid| cid.to_string()),
+                        }
+                    })
+                    .collect();
+
+                SubnetCheckpointStatus {
+                    child: child.id.to_string(),
+                    parent: parent.id.to_string(),
+                    check_period: status.check_period,
+                    last_seen_epoch: status.last_seen_epoch,
+                    last_processed_epoch: status.last_processed_epoch,
+                    restarts: status.restarts,
+                    last_error: status.last_error,
+                    accounts,
+                }
+            })
+            .collect();
+
+        Ok(CheckpointStatusResponse { subnets })
+    }
+}
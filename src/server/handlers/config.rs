@@ -5,10 +5,11 @@
 use crate::config::ReloadableConfig;
 use crate::server::JsonRPCRequestHandler;
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct ReloadConfigParams {
     pub path: Option<String>,
 }
@@ -4,6 +4,7 @@
 
 use crate::config::DEFAULT_IPC_GATEWAY_ADDR;
 use crate::manager::SubnetManager;
+use crate::server::handlers::manager::receipt::{TxOptions, TxReceipt, TxResponse};
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
 use anyhow::anyhow;
@@ -12,11 +13,12 @@ use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use ipc_sdk::subnet_id::SubnetID;
 use ipc_subnet_actor::{ConsensusType, ConstructParams};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSubnetParams {
     pub parent: String,
     pub name: String,
@@ -24,12 +26,17 @@ pub struct CreateSubnetParams {
     pub min_validators: u64,
     pub finality_threshold: ChainEpoch,
     pub check_period: ChainEpoch,
+    #[serde(flatten)]
+    pub tx: TxOptions,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CreateSubnetResponse {
-    /// The address of the created subnet
-    pub address: String,
+    /// The address of the created subnet, unknown until the creation message is executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// The receipt of the subnet creation message
+    pub receipt: TxResponse,
 }
 
 /// The create subnet json rpc method handler.
@@ -68,13 +75,29 @@ impl JsonRPCRequestHandler for CreateSubnetHandler {
             genesis: vec![],
         };
 
-        let created_subnet_addr = conn
+        let from = conn.subnet().accounts[0];
+        if !request.tx.wait {
+            let cid = conn
+                .submit(move |manager| async move {
+                    manager.create_subnet(from, constructor_params).await
+                })
+                .await?;
+            return Ok(CreateSubnetResponse {
+                address: None,
+                receipt: TxResponse::Submitted {
+                    message_cid: cid.to_string(),
+                },
+            });
+        }
+
+        let (created_subnet_addr, response) = conn
             .manager()
-            .create_subnet(conn.subnet().accounts[0], constructor_params)
+            .create_subnet(from, constructor_params)
             .await?;
 
         Ok(CreateSubnetResponse {
-            address: created_subnet_addr.to_string(),
+            address: Some(created_subnet_addr.to_string()),
+            receipt: TxResponse::Executed(TxReceipt::try_from(&response)?),
         })
     }
 }
@@ -3,6 +3,7 @@
 //! Create subnet handler and parameters
 
 use crate::manager::SubnetManager;
+use crate::server::handlers::manager::receipt::{TxOptions, TxReceipt, TxResponse};
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
 use anyhow::anyhow;
@@ -11,16 +12,19 @@ use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use ipc_sdk::subnet_id::SubnetID;
 use ipc_subnet_actor::JoinParams;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JoinSubnetParams {
     pub subnet: String,
     pub from: Option<String>,
     pub collateral: u64,
     pub validator_net_addr: String,
+    #[serde(flatten)]
+    pub tx: TxOptions,
 }
 
 /// The create subnet json rpc method handler.
@@ -37,7 +41,7 @@ impl JoinSubnetHandler {
 #[async_trait]
 impl JsonRPCRequestHandler for JoinSubnetHandler {
     type Request = JoinSubnetParams;
-    type Response = ();
+    type Response = TxResponse;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
@@ -59,8 +63,24 @@ impl JsonRPCRequestHandler for JoinSubnetHandler {
             None => conn.subnet().accounts[0],
         };
 
-        conn.manager()
+        if !request.tx.wait {
+            let cid = conn
+                .submit(move |manager| async move {
+                    manager
+                        .join_subnet(subnet, from, collateral, join_params)
+                        .await
+                })
+                .await?;
+            return Ok(TxResponse::Submitted {
+                message_cid: cid.to_string(),
+            });
+        }
+
+        let response = conn
+            .manager()
             .join_subnet(subnet, from, collateral, join_params)
-            .await
+            .await?;
+
+        Ok(TxResponse::Executed(TxReceipt::try_from(&response)?))
     }
 }
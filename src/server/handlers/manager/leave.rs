@@ -3,20 +3,24 @@
 //! Create subnet handler and parameters
 
 use crate::manager::SubnetManager;
+use crate::server::handlers::manager::receipt::{TxOptions, TxReceipt, TxResponse};
 use crate::server::handlers::manager::subnet::SubnetManagerPool;
 use crate::server::JsonRPCRequestHandler;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use fvm_shared::address::Address;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LeaveSubnetParams {
     pub subnet: String,
     pub from: Option<String>,
+    #[serde(flatten)]
+    pub tx: TxOptions,
 }
 
 /// The create subnet json rpc method handler.
@@ -33,7 +37,7 @@ impl LeaveSubnetHandler {
 #[async_trait]
 impl JsonRPCRequestHandler for LeaveSubnetHandler {
     type Request = LeaveSubnetParams;
-    type Response = ();
+    type Response = TxResponse;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
         let subnet = SubnetID::from_str(&request.subnet)?;
@@ -52,6 +56,17 @@ impl JsonRPCRequestHandler for LeaveSubnetHandler {
             None => conn.subnet().accounts[0],
         };
 
-        conn.manager().leave_subnet(subnet, from).await
+        if !request.tx.wait {
+            let cid = conn
+                .submit(move |manager| async move { manager.leave_subnet(subnet, from).await })
+                .await?;
+            return Ok(TxResponse::Submitted {
+                message_cid: cid.to_string(),
+            });
+        }
+
+        let response = conn.manager().leave_subnet(subnet, from).await?;
+
+        Ok(TxResponse::Executed(TxReceipt::try_from(&response)?))
     }
 }
@@ -1,8 +1,13 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 pub mod create;
+pub mod fund;
 pub mod join;
 pub mod kill;
 pub mod leave;
 pub mod list_subnets;
+pub mod propagate;
+pub mod receipt;
+pub mod release;
 pub mod subnet;
+pub mod whitelist;
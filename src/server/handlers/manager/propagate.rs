@@ -0,0 +1,75 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Propagate handler and parameters
+
+use crate::manager::SubnetManager;
+use crate::server::handlers::manager::receipt::{TxOptions, TxReceipt, TxResponse};
+use crate::server::handlers::manager::subnet::SubnetManagerPool;
+use crate::server::JsonRPCRequestHandler;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use cid::Cid;
+use fvm_shared::address::Address;
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PropagateParams {
+    pub subnet: String,
+    pub from: Option<String>,
+    pub postbox_msg_cid: String,
+    #[serde(flatten)]
+    pub tx: TxOptions,
+}
+
+/// The propagate json rpc method handler.
+pub(crate) struct PropagateHandler {
+    pool: Arc<SubnetManagerPool>,
+}
+
+impl PropagateHandler {
+    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for PropagateHandler {
+    type Request = PropagateParams;
+    type Response = TxResponse;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let subnet = SubnetID::from_str(&request.subnet)?;
+        let conn = match self.pool.get(&request.subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let postbox_msg_cid = Cid::from_str(&request.postbox_msg_cid)?;
+        let from = match request.from {
+            Some(addr) => Address::from_str(&addr)?,
+            None => conn.subnet().accounts[0],
+        };
+
+        if !request.tx.wait {
+            let cid = conn
+                .submit(move |manager| async move {
+                    manager.propagate(subnet, from, postbox_msg_cid).await
+                })
+                .await?;
+            return Ok(TxResponse::Submitted {
+                message_cid: cid.to_string(),
+            });
+        }
+
+        let response = conn
+            .manager()
+            .propagate(subnet, from, postbox_msg_cid)
+            .await?;
+
+        Ok(TxResponse::Executed(TxReceipt::try_from(&response)?))
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The receipts of the messages sent by the subnet lifecycle methods
+
+use std::fmt::{Display, Formatter};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::lotus::message::state::StateWaitMsgResponse;
+
+/// The receipt of a message executed on chain, as returned by the subnet lifecycle methods.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TxReceipt {
+    /// The cid of the message
+    pub message_cid: String,
+    /// The cids of the blocks of the tip set in which the message was executed
+    pub tip_set: Vec<String>,
+    /// The height of the tip set in which the message was executed
+    pub height: u64,
+    /// The gas consumed by the message execution
+    pub gas_used: u64,
+    /// The exit code of the message execution
+    pub exit_code: u32,
+}
+
+/// The response of the subnet lifecycle methods. It holds the receipt of the executed message, or
+/// only the cid of the message if the request did not wait for its execution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TxResponse {
+    Executed(TxReceipt),
+    Submitted { message_cid: String },
+}
+
+/// The options of the subnet lifecycle methods on how their message is sent, flattened into their
+/// parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TxOptions {
+    /// Whether to wait for the execution of the message, defaults to true. Otherwise, only the
+    /// cid of the message is returned, and its status is reported by `ipc_messageStatus`.
+    #[serde(default = "default_wait")]
+    pub wait: bool,
+}
+
+// The subnet lifecycle methods wait for the execution of their message unless told otherwise.
+fn default_wait() -> bool {
+    true
+}
+
+impl TryFrom<&StateWaitMsgResponse> for TxReceipt {
+    type Error = anyhow::Error;
+
+    fn try_from(response: &StateWaitMsgResponse) -> Result<Self, Self::Error> {
+        Ok(TxReceipt {
+            message_cid: response.message_cid()?.to_string(),
+            tip_set: response
+                .tip_set()?
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            height: response.height(),
+            gas_used: response.gas_used(),
+            exit_code: response.exit_code(),
+        })
+    }
+}
+
+impl Display for TxReceipt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "message cid: {}, tip set: [{}], height: {}, gas used: {}, exit code: {}",
+            self.message_cid,
+            self.tip_set.join(", "),
+            self.height,
+            self.gas_used,
+            self.exit_code
+        )
+    }
+}
+
+impl Display for TxResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxResponse::Executed(receipt) => write!(f, "{receipt}"),
+            TxResponse::Submitted { message_cid } => {
+                write!(f, "message cid: {message_cid}, not executed yet")
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Release funds handler and parameters
+
+use crate::manager::SubnetManager;
+use crate::server::handlers::manager::receipt::{TxOptions, TxReceipt, TxResponse};
+use crate::server::handlers::manager::subnet::SubnetManagerPool;
+use crate::server::JsonRPCRequestHandler;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The parameters of the release funds method. The cid of the release message is used to track
+/// the withdrawal, whether or not the request waits for its execution.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseFundsParams {
+    pub subnet: String,
+    pub from: Option<String>,
+    pub amount: u64,
+    #[serde(flatten)]
+    pub tx: TxOptions,
+}
+
+/// The release funds json rpc method handler.
+pub(crate) struct ReleaseFundsHandler {
+    pool: Arc<SubnetManagerPool>,
+}
+
+impl ReleaseFundsHandler {
+    pub(crate) fn new(pool: Arc<SubnetManagerPool>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for ReleaseFundsHandler {
+    type Request = ReleaseFundsParams;
+    type Response = TxResponse;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let subnet = SubnetID::from_str(&request.subnet)?;
+
+        // Funds are released from the child subnet, so we connect to the subnet itself
+        // instead of its parent.
+        let conn = match self.pool.get(&request.subnet) {
+            None => return Err(anyhow!("target subnet not found")),
+            Some(conn) => conn,
+        };
+
+        let amount = TokenAmount::from_atto(request.amount);
+        let from = match request.from {
+            Some(addr) => Address::from_str(&addr)?,
+            None => conn.subnet().accounts[0],
+        };
+
+        if !request.tx.wait {
+            let cid = conn
+                .submit(move |manager| async move { manager.release(subnet, from, amount).await })
+                .await?;
+            return Ok(TxResponse::Submitted {
+                message_cid: cid.to_string(),
+            });
+        }
+
+        let response = conn.manager().release(subnet, from, amount).await?;
+
+        Ok(TxResponse::Executed(TxReceipt::try_from(&response)?))
+    }
+}
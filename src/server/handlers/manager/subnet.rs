@@ -3,17 +3,23 @@
 //! The shared subnet manager module for all subnet management related RPC method calls.
 
 use crate::config::{ReloadableConfig, Subnet};
-use crate::jsonrpc::{JsonRpcClient, JsonRpcClientImpl};
-use crate::manager::LotusSubnetManager;
+use crate::jsonrpc::JsonRpcClientImpl;
+use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::LotusClient;
+use crate::manager::{LotusSubnetManager, MessageTracker, SubnetEvent};
+use anyhow::anyhow;
+use cid::Cid;
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::{broadcast, oneshot};
 
 /// The subnet manager connection that holds the subnet config and the manager instance.
-pub struct Connection<T: JsonRpcClient> {
+pub struct Connection<T: LotusClient> {
     subnet: Subnet,
     manager: LotusSubnetManager<T>,
 }
 
-impl<T: JsonRpcClient> Connection<T> {
+impl<T: LotusClient> Connection<T> {
     pub fn subnet(&self) -> &Subnet {
         &self.subnet
     }
@@ -23,27 +29,67 @@ impl<T: JsonRpcClient> Connection<T> {
     }
 }
 
+impl<T: LotusClient + 'static> Connection<T> {
+    /// Runs `send`, which sends a single message with the manager of the connection, in the
+    /// background. Returns the cid of the message as soon as it is pushed to the mpool, without
+    /// waiting for its execution, which is reported to the message tracker of the pool.
+    pub async fn submit<F, Fut, R>(self, send: F) -> anyhow::Result<Cid>
+    where
+        F: FnOnce(Arc<LotusSubnetManager<T>>) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (on_submitted, submitted) = oneshot::channel();
+        let manager = Arc::new(self.manager.notify_submitted(on_submitted));
+        let task = tokio::spawn(send(manager));
+
+        match submitted.await {
+            Ok(cid) => Ok(cid),
+            // The manager failed before pushing the message, report its error.
+            Err(_) => match task.await? {
+                Ok(_) => Err(anyhow!("no message was submitted")),
+                Err(e) => Err(e),
+            },
+        }
+    }
+}
+
 /// The json rpc subnet manager connection pool. This struct can be shared by all the subnet methods.
 /// As such, there is no need to re-init the same SubnetManager for different methods to reuse connections.
 pub struct SubnetManagerPool {
     config: Arc<ReloadableConfig>,
+    /// Records the status of the messages sent by the managers of the pool.
+    message_tracker: Arc<MessageTracker>,
+    /// Receives the subnet events caused by the messages of the managers of the pool.
+    subnet_events: broadcast::Sender<SubnetEvent>,
 }
 
 impl SubnetManagerPool {
-    pub fn from_reload_config(reload_config: Arc<ReloadableConfig>) -> Self {
+    pub fn from_reload_config(
+        reload_config: Arc<ReloadableConfig>,
+        message_tracker: Arc<MessageTracker>,
+        subnet_events: broadcast::Sender<SubnetEvent>,
+    ) -> Self {
         Self {
             config: reload_config,
+            message_tracker,
+            subnet_events,
         }
     }
 
     /// Get the connection instance for the subnet.
-    pub fn get(&self, subnet_str: &str) -> Option<Connection<JsonRpcClientImpl>> {
+    pub fn get(
+        &self,
+        subnet_str: &str,
+    ) -> Option<Connection<LotusJsonRPCClient<JsonRpcClientImpl>>> {
         let config = self.config.get_config();
         let subnets = &config.subnets;
 
         match subnets.get(subnet_str) {
             Some(subnet) => {
-                let manager = LotusSubnetManager::from_subnet(subnet);
+                let manager = LotusSubnetManager::from_subnet(subnet)
+                    .with_message_tracker(self.message_tracker.clone())
+                    .with_event_sender(self.subnet_events.clone());
                 Some(Connection {
                     manager,
                     subnet: subnet.clone(),
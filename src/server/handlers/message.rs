@@ -0,0 +1,104 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! Expose the status of the messages submitted by the agent
+
+use crate::manager::{MessageStatus, MessageTracker};
+use crate::server::handlers::manager::receipt::TxReceipt;
+use crate::server::JsonRPCRequestHandler;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use cid::Cid;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MessageStatusParams {
+    /// Only return the status of this message, optional
+    pub message_cid: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MessageStatusResponse {
+    /// The status of the tracked messages, in submission order
+    pub messages: Vec<TrackedMessageStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageState {
+    Pending,
+    Executed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TrackedMessageStatus {
+    pub message_cid: String,
+    pub state: MessageState,
+    /// The receipt of the message, if it was executed
+    pub receipt: Option<TxReceipt>,
+    /// The reason of the failure, if the message failed
+    pub error: Option<String>,
+}
+
+/// The message status json rpc method handler.
+pub(crate) struct MessageStatusHandler {
+    tracker: Arc<MessageTracker>,
+}
+
+impl MessageStatusHandler {
+    pub(crate) fn new(tracker: Arc<MessageTracker>) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait]
+impl JsonRPCRequestHandler for MessageStatusHandler {
+    type Request = MessageStatusParams;
+    type Response = MessageStatusResponse;
+
+    async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
+        let statuses = match request.message_cid {
+            Some(cid) => {
+                let cid = Cid::from_str(&cid)?;
+                let status = self
+                    .tracker
+                    .status(&cid)
+                    .ok_or_else(|| anyhow!("message {cid} is not tracked by the agent"))?;
+                vec![(cid, status)]
+            }
+            None => self.tracker.statuses(),
+        };
+
+        let messages = statuses
+            .into_iter()
+            .map(|(cid, status)| -> anyhow::Result<TrackedMessageStatus> {
+                let message_cid = cid.to_string();
+                Ok(match status {
+                    MessageStatus::Pending => TrackedMessageStatus {
+                        message_cid,
+                        state: MessageState::Pending,
+                        receipt: None,
+                        error: None,
+                    },
+                    MessageStatus::Executed(response) => TrackedMessageStatus {
+                        message_cid,
+                        state: MessageState::Executed,
+                        receipt: Some(TxReceipt::try_from(&response)?),
+                        error: None,
+                    },
+                    MessageStatus::Failed(error) => TrackedMessageStatus {
+                        message_cid,
+                        state: MessageState::Failed,
+                        receipt: None,
+                        error: Some(error),
+                    },
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(MessageStatusResponse { messages })
+    }
+}
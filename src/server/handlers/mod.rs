@@ -2,21 +2,36 @@
 // SPDX-License-Identifier: MIT
 //! The module contains the handlers implementation for the json rpc server.
 
+mod checkpoint;
 mod config;
 mod manager;
+mod message;
+mod subscription;
 mod validator;
 
 use crate::config::json_rpc_methods;
 use crate::config::ReloadableConfig;
+use crate::manager::{subnet_event_channel, CheckpointTracker, MessageTracker};
+use crate::server::handlers::checkpoint::CheckpointStatusHandler;
 use crate::server::handlers::config::ReloadConfigHandler;
 use crate::server::handlers::manager::list_subnets::ListSubnetsHandler;
+use crate::server::handlers::message::MessageStatusHandler;
+pub(crate) use crate::server::handlers::subscription::Subscription;
+use crate::server::handlers::subscription::Subscriptions;
 use crate::server::handlers::validator::QueryValidatorSetHandler;
-use crate::server::JsonRPCRequestHandler;
-use anyhow::{anyhow, Result};
+use crate::server::openrpc::{OpenRpcBuilder, OpenRpcDocument};
+use crate::server::{JsonRPCRequestHandler, RequestError};
+use anyhow::Result;
 use async_trait::async_trait;
+pub use checkpoint::{
+    AccountCheckpointStatus, CheckpointStatusParams, CheckpointStatusResponse,
+    SubnetCheckpointStatus,
+};
 pub use config::ReloadConfigParams;
 use manager::create::CreateSubnetHandler;
 pub use manager::create::{CreateSubnetParams, CreateSubnetResponse};
+use manager::fund::FundSubnetHandler;
+pub use manager::fund::FundSubnetParams;
 use manager::join::JoinSubnetHandler;
 pub use manager::join::JoinSubnetParams;
 use manager::kill::KillSubnetHandler;
@@ -24,31 +39,59 @@ pub use manager::kill::KillSubnetParams;
 use manager::leave::LeaveSubnetHandler;
 pub use manager::leave::LeaveSubnetParams;
 pub use manager::list_subnets::ListSubnetsParams;
+use manager::propagate::PropagateHandler;
+pub use manager::propagate::PropagateParams;
+pub use manager::receipt::{TxOptions, TxReceipt, TxResponse};
+use manager::release::ReleaseFundsHandler;
+pub use manager::release::ReleaseFundsParams;
 use manager::subnet::SubnetManagerPool;
+use manager::whitelist::WhitelistPropagatorHandler;
+pub use manager::whitelist::WhitelistPropagatorParams;
+pub use message::{MessageState, MessageStatusParams, MessageStatusResponse, TrackedMessageStatus};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
+pub use subscription::{
+    CheckpointNotification, SubnetEventNotification, SubnetEventType, SubscribeParams,
+    SubscriptionNotification, UnsubscribeParams,
+};
 
 pub type Method = String;
 
-/// The collection of all json rpc handlers
+/// The collection of all json rpc handlers, along with the subscriptions served through the
+/// websocket transport
 pub struct Handlers {
     handlers: HashMap<Method, Box<dyn HandlerWrapper>>,
+    subscriptions: Subscriptions,
+    /// The OpenRPC document of the handlers and of the subscriptions, returned by `rpc.discover`.
+    openrpc: OpenRpcDocument,
 }
 
 /// A util trait to avoid Box<dyn> and associated type mess in Handlers struct
 #[async_trait]
 trait HandlerWrapper: Send + Sync {
     async fn handle(&self, params: Value) -> Result<Value>;
+
+    /// Returns the schemas of the params and of the result of the handler, generated by `gen`.
+    fn schemas(&self, gen: &mut SchemaGenerator) -> (Schema, Schema);
 }
 
 #[async_trait]
 impl<H: JsonRPCRequestHandler + Send + Sync> HandlerWrapper for H {
     async fn handle(&self, params: Value) -> Result<Value> {
-        let p = serde_json::from_value(params)?;
+        let p = serde_json::from_value(params).map_err(RequestError::InvalidParams)?;
         let r = self.handle(p).await?;
         Ok(serde_json::to_value(r)?)
     }
+
+    fn schemas(&self, gen: &mut SchemaGenerator) -> (Schema, Schema) {
+        (
+            gen.subschema_for::<H::Request>(),
+            gen.subschema_for::<H::Response>(),
+        )
+    }
 }
 
 impl Handlers {
@@ -56,21 +99,38 @@ impl Handlers {
     /// Convenient method for json rpc to test routing.
     #[cfg(test)]
     pub fn empty_handlers() -> Self {
+        let handlers = HashMap::new();
+        let openrpc = openrpc_document(&handlers);
         Self {
-            handlers: HashMap::new(),
+            handlers,
+            subscriptions: Subscriptions::new(
+                Arc::new(CheckpointTracker::default()),
+                subnet_event_channel(),
+            ),
+            openrpc,
         }
     }
 
-    pub fn new(config_path_string: String) -> Result<Self> {
+    pub fn new(
+        config: Arc<ReloadableConfig>,
+        config_path_string: String,
+        checkpoint_tracker: Arc<CheckpointTracker>,
+    ) -> Result<Self> {
         let mut handlers = HashMap::new();
 
-        let config = Arc::new(ReloadableConfig::new(config_path_string.clone())?);
         let h: Box<dyn HandlerWrapper> =
             Box::new(ReloadConfigHandler::new(config.clone(), config_path_string));
         handlers.insert(String::from(json_rpc_methods::RELOAD_CONFIG), h);
 
-        // subnet manager methods
-        let pool = Arc::new(SubnetManagerPool::from_reload_config(config.clone()));
+        // subnet manager methods, which report the status of the messages they send to the
+        // message tracker
+        let message_tracker = Arc::new(MessageTracker::default());
+        let subnet_events = subnet_event_channel();
+        let pool = Arc::new(SubnetManagerPool::from_reload_config(
+            config.clone(),
+            message_tracker.clone(),
+            subnet_events.clone(),
+        ));
         let h: Box<dyn HandlerWrapper> = Box::new(CreateSubnetHandler::new(pool.clone()));
         handlers.insert(String::from(json_rpc_methods::CREATE_SUBNET), h);
 
@@ -83,21 +143,154 @@ impl Handlers {
         let h: Box<dyn HandlerWrapper> = Box::new(JoinSubnetHandler::new(pool.clone()));
         handlers.insert(String::from(json_rpc_methods::JOIN_SUBNET), h);
 
+        let h: Box<dyn HandlerWrapper> = Box::new(FundSubnetHandler::new(pool.clone()));
+        handlers.insert(String::from(json_rpc_methods::FUND_SUBNET), h);
+
+        let h: Box<dyn HandlerWrapper> = Box::new(ReleaseFundsHandler::new(pool.clone()));
+        handlers.insert(String::from(json_rpc_methods::RELEASE_FUNDS), h);
+
+        let h: Box<dyn HandlerWrapper> = Box::new(PropagateHandler::new(pool.clone()));
+        handlers.insert(String::from(json_rpc_methods::PROPAGATE), h);
+
+        let h: Box<dyn HandlerWrapper> = Box::new(WhitelistPropagatorHandler::new(pool.clone()));
+        handlers.insert(String::from(json_rpc_methods::WHITELIST_PROPAGATOR), h);
+
         let h: Box<dyn HandlerWrapper> = Box::new(ListSubnetsHandler::new(pool));
         handlers.insert(String::from(json_rpc_methods::LIST_CHILD_SUBNETS), h);
 
         // query validator
-        let h: Box<dyn HandlerWrapper> = Box::new(QueryValidatorSetHandler::new(config));
+        let h: Box<dyn HandlerWrapper> = Box::new(QueryValidatorSetHandler::new(config.clone()));
         handlers.insert(String::from(json_rpc_methods::QUERY_VALIDATOR_SET), h);
 
-        Ok(Self { handlers })
+        // message status
+        let h: Box<dyn HandlerWrapper> = Box::new(MessageStatusHandler::new(message_tracker));
+        handlers.insert(String::from(json_rpc_methods::MESSAGE_STATUS), h);
+
+        // checkpoint subsystem status
+        let h: Box<dyn HandlerWrapper> = Box::new(CheckpointStatusHandler::new(
+            config,
+            checkpoint_tracker.clone(),
+        ));
+        handlers.insert(String::from(json_rpc_methods::CHECKPOINT_STATUS), h);
+
+        let subscriptions = Subscriptions::new(checkpoint_tracker, subnet_events);
+        let openrpc = openrpc_document(&handlers);
+
+        Ok(Self {
+            handlers,
+            subscriptions,
+            openrpc,
+        })
+    }
+
+    /// Returns the OpenRPC document of the methods of the handlers and of the subscriptions.
+    pub fn openrpc(&self) -> &OpenRpcDocument {
+        &self.openrpc
     }
 
     pub async fn handle(&self, method: Method, params: Value) -> Result<Value> {
+        // The document is the same whatever the params.
+        if method == json_rpc_methods::RPC_DISCOVER {
+            return Ok(serde_json::to_value(&self.openrpc)?);
+        }
+
         if let Some(wrapper) = self.handlers.get(&method) {
             wrapper.handle(params).await
         } else {
-            Err(anyhow!("method not supported"))
+            Err(RequestError::MethodNotFound(method).into())
         }
     }
+
+    /// Subscribes to the events of the subscription `method` with `params`. Returns `None` if
+    /// `method` is not a subscription method.
+    pub(crate) fn subscribe(&self, method: &str, params: Value) -> Option<Result<Subscription>> {
+        self.subscriptions.subscribe(method, params)
+    }
+}
+
+fn openrpc_document(handlers: &HashMap<Method, Box<dyn HandlerWrapper>>) -> OpenRpcDocument {
+    let mut builder = OpenRpcBuilder::default();
+    for (method, handler) in handlers {
+        let (params, result) = handler.schemas(builder.generator());
+        builder.add_method(method, params, result);
+    }
+
+    // The subscriptions are served through the websocket transport only.
+    let params = builder.generator().subschema_for::<SubscribeParams>();
+    let notification = builder
+        .generator()
+        .subschema_for::<CheckpointNotification>();
+    builder.add_subscription(
+        json_rpc_methods::SUBSCRIBE_CHECKPOINTS,
+        params.clone(),
+        notification,
+    );
+    let notification = builder
+        .generator()
+        .subschema_for::<SubnetEventNotification>();
+    builder.add_subscription(
+        json_rpc_methods::SUBSCRIBE_SUBNET_EVENTS,
+        params,
+        notification,
+    );
+
+    let params = builder.generator().subschema_for::<UnsubscribeParams>();
+    let result = builder.generator().subschema_for::<bool>();
+    builder.add_method(json_rpc_methods::UNSUBSCRIBE, params, result);
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{json_rpc_methods, ReloadableConfig};
+    use crate::manager::CheckpointTracker;
+    use crate::server::Handlers;
+    use std::collections::HashSet;
+    use std::io::Write;
+    use std::sync::Arc;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn openrpc_lists_every_method() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"[server]\njson_rpc_address = \"127.0.0.1:3030\"\n\n[subnets]\n")
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+        let config = Arc::new(ReloadableConfig::new(path).unwrap());
+        let handlers = Handlers::new(
+            config,
+            String::from(path),
+            Arc::new(CheckpointTracker::default()),
+        )
+        .unwrap();
+
+        let names = handlers
+            .openrpc()
+            .methods
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<HashSet<_>>();
+        // `ipc_subscription` is the method of the notifications sent by the server.
+        let methods = HashSet::from([
+            json_rpc_methods::CREATE_SUBNET,
+            json_rpc_methods::JOIN_SUBNET,
+            json_rpc_methods::LEAVE_SUBNET,
+            json_rpc_methods::KILL_SUBNET,
+            json_rpc_methods::FUND_SUBNET,
+            json_rpc_methods::RELEASE_FUNDS,
+            json_rpc_methods::PROPAGATE,
+            json_rpc_methods::WHITELIST_PROPAGATOR,
+            json_rpc_methods::LIST_CHILD_SUBNETS,
+            json_rpc_methods::RELOAD_CONFIG,
+            json_rpc_methods::QUERY_VALIDATOR_SET,
+            json_rpc_methods::CHECKPOINT_STATUS,
+            json_rpc_methods::MESSAGE_STATUS,
+            json_rpc_methods::SUBSCRIBE_CHECKPOINTS,
+            json_rpc_methods::SUBSCRIBE_SUBNET_EVENTS,
+            json_rpc_methods::UNSUBSCRIBE,
+            json_rpc_methods::RPC_DISCOVER,
+        ]);
+        assert_eq!(names, methods);
+    }
 }
@@ -0,0 +1,193 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The server-push subscriptions served through the websocket transport
+
+use crate::config::json_rpc_methods;
+use crate::manager::{CheckpointEvent, CheckpointTracker, SubnetEvent, SubnetEventKind};
+use crate::server::RequestError;
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SubscribeParams {
+    /// Only notify the events of this subnet, optional
+    pub subnet: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UnsubscribeParams {
+    /// The id of the subscription to cancel
+    pub subscription: u64,
+}
+
+/// The params of the notifications of a subscription.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SubscriptionNotification {
+    /// The id of the subscription, as returned by the subscribe method
+    pub subscription: u64,
+    pub result: Value,
+}
+
+/// The notification of `ipc_subscribeCheckpoints`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CheckpointNotification {
+    /// The child subnet of the checkpoint
+    pub subnet: String,
+    pub account: String,
+    pub epoch: i64,
+    /// The cid of the checkpoint message, if it was submitted by the agent
+    pub message_cid: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubnetEventType {
+    Created,
+    Joined,
+    Left,
+    Killed,
+    Funded,
+    Released,
+}
+
+/// The notification of `ipc_subscribeSubnetEvents`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SubnetEventNotification {
+    pub event: SubnetEventType,
+    /// The subnet of the event, which is the parent subnet for a creation
+    pub subnet: String,
+    pub from: String,
+    pub message_cid: String,
+    /// The address of the created subnet actor, for a creation
+    pub actor: Option<String>,
+}
+
+impl From<CheckpointEvent> for CheckpointNotification {
+    fn from(event: CheckpointEvent) -> Self {
+        Self {
+            subnet: event.subnet.to_string(),
+            account: event.account.to_string(),
+            epoch: event.epoch,
+            message_cid: event.message_cid.map(|cid| cid.to_string()),
+        }
+    }
+}
+
+impl From<SubnetEvent> for SubnetEventNotification {
+    fn from(event: SubnetEvent) -> Self {
+        let (event_type, actor) = match event.kind {
+            SubnetEventKind::Created { actor } => (SubnetEventType::Created, Some(actor)),
+            SubnetEventKind::Joined => (SubnetEventType::Joined, None),
+            SubnetEventKind::Left => (SubnetEventType::Left, None),
+            SubnetEventKind::Killed => (SubnetEventType::Killed, None),
+            SubnetEventKind::Funded => (SubnetEventType::Funded, None),
+            SubnetEventKind::Released => (SubnetEventType::Released, None),
+        };
+        Self {
+            event: event_type,
+            subnet: event.subnet.to_string(),
+            from: event.from.to_string(),
+            message_cid: event.message_cid.to_string(),
+            actor: actor.map(|a| a.to_string()),
+        }
+    }
+}
+
+/// The sources of the events that can be subscribed to.
+pub(crate) struct Subscriptions {
+    checkpoint_tracker: Arc<CheckpointTracker>,
+    subnet_events: broadcast::Sender<SubnetEvent>,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(
+        checkpoint_tracker: Arc<CheckpointTracker>,
+        subnet_events: broadcast::Sender<SubnetEvent>,
+    ) -> Self {
+        Self {
+            checkpoint_tracker,
+            subnet_events,
+        }
+    }
+
+    /// Subscribes to the events of the subscription `method` with `params`. Returns `None` if
+    /// `method` is not a subscription method.
+    pub(crate) fn subscribe(&self, method: &str, params: Value) -> Option<Result<Subscription>> {
+        let receiver = match method {
+            json_rpc_methods::SUBSCRIBE_CHECKPOINTS => {
+                EventReceiver::Checkpoints(self.checkpoint_tracker.subscribe())
+            }
+            json_rpc_methods::SUBSCRIBE_SUBNET_EVENTS => {
+                EventReceiver::SubnetEvents(self.subnet_events.subscribe())
+            }
+            _ => return None,
+        };
+
+        let params = match serde_json::from_value::<Option<SubscribeParams>>(params) {
+            Ok(params) => params.unwrap_or_default(),
+            Err(e) => return Some(Err(RequestError::InvalidParams(e).into())),
+        };
+        Some(Ok(Subscription {
+            receiver,
+            subnet: params.subnet,
+        }))
+    }
+}
+
+enum EventReceiver {
+    Checkpoints(broadcast::Receiver<CheckpointEvent>),
+    SubnetEvents(broadcast::Receiver<SubnetEvent>),
+}
+
+/// A subscription to a source of events, optionally restricted to the events of a subnet.
+pub(crate) struct Subscription {
+    receiver: EventReceiver,
+    subnet: Option<String>,
+}
+
+impl Subscription {
+    /// Returns the next notification of the subscription, or `None` once the source of the
+    /// events is closed. A subscriber that lags behind misses the oldest events.
+    pub(crate) async fn recv(&mut self) -> Option<Value> {
+        loop {
+            let (subnet, notification) = match &mut self.receiver {
+                EventReceiver::Checkpoints(r) => match next_event(r).await? {
+                    Some(e) => (
+                        e.subnet.to_string(),
+                        serde_json::to_value(CheckpointNotification::from(e)),
+                    ),
+                    None => continue,
+                },
+                EventReceiver::SubnetEvents(r) => match next_event(r).await? {
+                    Some(e) => (
+                        e.subnet.to_string(),
+                        serde_json::to_value(SubnetEventNotification::from(e)),
+                    ),
+                    None => continue,
+                },
+            };
+
+            if self.subnet.as_ref().map_or(true, |s| *s == subnet) {
+                return notification.ok();
+            }
+        }
+    }
+}
+
+/// Receives the next event of `receiver`. Returns `None` if the channel is closed, and
+/// `Some(None)` if events were missed because the subscriber lagged behind.
+async fn next_event<E: Clone>(receiver: &mut broadcast::Receiver<E>) -> Option<Option<E>> {
+    match receiver.recv().await {
+        Ok(event) => Some(Some(event)),
+        Err(RecvError::Lagged(n)) => {
+            log::warn!("subscriber lagged behind, {n} events were dropped");
+            Some(None)
+        }
+        Err(RecvError::Closed) => None,
+    }
+}
@@ -4,24 +4,26 @@
 
 use crate::config::ReloadableConfig;
 use crate::lotus::client::LotusJsonRPCClient;
+use crate::lotus::message::chain::TipSetKey;
 use crate::lotus::message::ipc::ValidatorSet;
 use crate::lotus::LotusClient;
 use crate::server::JsonRPCRequestHandler;
 use anyhow::anyhow;
 use async_trait::async_trait;
-use cid::Cid;
 use ipc_sdk::subnet_id::SubnetID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct QueryValidatorSetParams {
     pub subnet: String,
+    /// The comma separated CIDs of the blocks of the tip set
     pub tip_set: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct QueryValidatorSetResponse {
     /// The address of the created subnet
     pub validator_set: ValidatorSet,
@@ -44,7 +46,7 @@ impl JsonRPCRequestHandler for QueryValidatorSetHandler {
     type Response = QueryValidatorSetResponse;
 
     async fn handle(&self, request: Self::Request) -> anyhow::Result<Self::Response> {
-        let tip_set = Cid::from_str(&request.tip_set)?;
+        let tip_set = TipSetKey::from_str(&request.tip_set)?;
         let subnet_id = SubnetID::from_str(&request.subnet)?;
         let parent = subnet_id
             .parent()
@@ -60,7 +62,7 @@ impl JsonRPCRequestHandler for QueryValidatorSetHandler {
 
         let lotus = LotusJsonRPCClient::from_subnet(subnet);
         let response = lotus
-            .ipc_read_subnet_actor_state(&subnet_id, tip_set)
+            .ipc_read_subnet_actor_state(&subnet_id, &tip_set)
             .await?;
 
         Ok(QueryValidatorSetResponse {
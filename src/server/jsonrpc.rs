@@ -1,17 +1,26 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
 use crate::config::JSON_RPC_ENDPOINT;
-use crate::config::{Config, JSON_RPC_VERSION};
-use crate::server::request::JSONRPCRequest;
-use crate::server::response::{JSONRPCError, JSONRPCErrorResponse, JSONRPCResultResponse};
+use crate::config::{Permission, ReloadableConfig};
+use crate::manager::CheckpointTracker;
+use crate::server::auth::{required_permission, with_permission, AuthConfig, Unauthorized};
+use crate::server::request::{Id, JSONRPCMessage, JSONRPCRequest};
+use crate::server::response::{
+    JSONRPCError, JSONRPCErrorResponse, JSONRPCResponse, JSONRPCResultResponse,
+    FORBIDDEN_ERROR_CODE,
+};
+use crate::server::ws::serve_ws;
 use crate::server::Handlers;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use bytes::Bytes;
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::Value;
 
 use std::sync::Arc;
+use tokio_graceful_shutdown::SubsystemHandle;
 use warp::http::StatusCode;
-use warp::reject::Reject;
-use warp::reply::with_status;
+use warp::reply::{with_status, Response};
 use warp::{Filter, Rejection, Reply};
 
 type ArcHandlers = Arc<Handlers>;
@@ -19,50 +28,101 @@ type ArcHandlers = Arc<Handlers>;
 /// The IPC JSON RPC node that contains all the methods and handlers. The underlying implementation
 /// is using `warp`.
 ///
-/// Note that currently only http json rpc is supported.
+/// The requests are accepted over http, and over websocket, which additionally supports
+/// server-push subscriptions. When the config has a `[server.auth]` section, they must carry a
+/// bearer token granting the permission required by their method.
 ///
 /// # Examples
 /// ```no_run
-/// use ipc_agent::config::Config;
+/// use std::time::Duration;
 /// use ipc_agent::server::jsonrpc::JsonRPCServer;
+/// use tokio_graceful_shutdown::{SubsystemHandle, Toplevel};
 ///
 /// #[tokio::main]
 /// async fn main() {
 ///     let path = "PATH TO YOUR CONFIG FILE";
 ///     let n = JsonRPCServer::from_config_path(path).unwrap();
-///     n.run().await;
+///     Toplevel::new()
+///         .start("JSON-RPC server", move |s: SubsystemHandle| async move { n.run(s).await })
+///         .catch_signals()
+///         .handle_shutdown_requests(Duration::from_secs(1))
+///         .await
+///         .unwrap();
 /// }
 /// ```
 pub struct JsonRPCServer {
-    config: Config,
+    config: Arc<ReloadableConfig>,
     /// The default path to reload config from
     default_config_path: String,
+    /// The tracker of the checkpoint subsystem running alongside the server
+    checkpoint_tracker: Arc<CheckpointTracker>,
 }
 
 impl JsonRPCServer {
-    pub fn new(config: Config, default_config_path: String) -> Self {
+    pub fn new(
+        config: Arc<ReloadableConfig>,
+        default_config_path: String,
+        checkpoint_tracker: Arc<CheckpointTracker>,
+    ) -> Self {
         Self {
             config,
             default_config_path,
+            checkpoint_tracker,
         }
     }
 
     pub fn from_config_path(config_path_str: &str) -> Result<Self> {
-        let config = Config::from_file(config_path_str)?;
-        Ok(Self::new(config, String::from(config_path_str)))
+        let config = Arc::new(ReloadableConfig::new(config_path_str)?);
+        Ok(Self::new(
+            config,
+            String::from(config_path_str),
+            Arc::new(CheckpointTracker::default()),
+        ))
     }
 
-    /// Runs the node in the current thread
-    pub async fn run(&self) -> Result<()> {
-        log::info!(
-            "IPC agent rpc node listening at {:?}",
-            self.config.server.json_rpc_address
-        );
+    /// Runs the node as a subsystem of `tokio-graceful-shutdown`. The server stops accepting
+    /// connections once a shutdown is requested through `subsys`.
+    pub async fn run(&self, subsys: SubsystemHandle) -> Result<()> {
+        let config = self.config.get_config();
+        let json_rpc_address = config.server.json_rpc_address;
+        log::info!("IPC agent rpc node listening at {:?}", json_rpc_address);
+
+        let handlers = Arc::new(Handlers::new(
+            self.config.clone(),
+            self.default_config_path.clone(),
+            self.checkpoint_tracker.clone(),
+        )?);
+        let reloadable_config = self.config.clone();
+        let auth: AuthConfig = Arc::new(move || reloadable_config.get_config().server.auth.clone());
+        let filter = json_rpc_filter(handlers, auth);
+        let shutdown = async move { subsys.on_shutdown_requested().await };
+
+        match config.server.tls()? {
+            Some((cert_path, key_path)) => {
+                // The files are read beforehand, as warp panics if it cannot read them.
+                let cert = tokio::fs::read(cert_path)
+                    .await
+                    .with_context(|| format!("cannot read tls certificate {cert_path:?}"))?;
+                let key = tokio::fs::read(key_path)
+                    .await
+                    .with_context(|| format!("cannot read tls key {key_path:?}"))?;
+                log::info!("IPC agent rpc node serving over tls");
+
+                let (_, server) = warp::serve(filter)
+                    .tls()
+                    .cert(cert)
+                    .key(key)
+                    .bind_with_graceful_shutdown(json_rpc_address, shutdown);
+                server.await;
+            }
+            None => {
+                let (_, server) =
+                    warp::serve(filter).bind_with_graceful_shutdown(json_rpc_address, shutdown);
+                server.await;
+            }
+        }
 
-        let handlers = Arc::new(Handlers::new(self.default_config_path.clone())?);
-        warp::serve(json_rpc_filter(handlers))
-            .run(self.config.server.json_rpc_address)
-            .await;
+        log::info!("IPC agent rpc node stopped");
         Ok(())
     }
 }
@@ -72,17 +132,35 @@ impl JsonRPCServer {
 /// Create the json_rpc filter. The filter does the following:
 /// - Listen to POST requests on the DEFAULT_JSON_RPC_ENDPOINT
 /// - Extract the body of the request.
-/// - Pass it to to the json_rpc_filter to deserialize into a jsonrpc request.
+/// - Pass it to to the json_rpc_filter to deserialize into a jsonrpc request, or a batch of them.
+///
+/// It also upgrades the websocket connections on the DEFAULT_JSON_RPC_ENDPOINT, over which the
+/// requests are served one message at a time.
+///
+/// Both transports authenticate the bearer token of the request, or of the upgrade request,
+/// against `auth`.
 fn json_rpc_filter(
     handlers: ArcHandlers,
+    auth: AuthConfig,
 ) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
-    warp::post()
+    let http = warp::post()
         .and(warp::path(JSON_RPC_ENDPOINT))
+        .and(with_permission(auth.clone()))
         .and(warp::body::bytes())
-        .and_then(to_json_rpc_request)
+        .and(with_handlers(handlers.clone()))
+        .and_then(handle_request);
+
+    let ws = warp::path(JSON_RPC_ENDPOINT)
+        .and(warp::ws())
+        .and(with_permission(auth))
         .and(with_handlers(handlers))
-        .and_then(handle_request)
-        .recover(handle_rejection)
+        .map(
+            |ws: warp::ws::Ws, permission: Permission, handlers: ArcHandlers| {
+                ws.on_upgrade(move |socket| serve_ws(socket, handlers, permission))
+            },
+        );
+
+    http.or(ws).recover(handle_rejection)
 }
 
 fn with_handlers(
@@ -91,92 +169,190 @@ fn with_handlers(
     warp::any().map(move || handlers.clone())
 }
 
-// Filter that deserializes the body of the request into a jsonrpc request.
-async fn to_json_rpc_request(bytes: Bytes) -> Result<JSONRPCRequest, warp::Rejection> {
-    serde_json::from_slice::<JSONRPCRequest>(bytes.as_ref()).map_err(|e| {
-        log::debug!("cannot deserialize {bytes:?} due to {e:?}");
-        warp::reject::custom(InvalidParameter)
-    })
+/// Replies to the jsonrpc message received over http, which is either a single request or a
+/// batch of them. Nothing is replied when all of its requests are notifications.
+async fn handle_request(
+    permission: Permission,
+    body: Bytes,
+    handlers: ArcHandlers,
+) -> Result<Response, warp::Rejection> {
+    let message = match JSONRPCMessage::from_slice(&body) {
+        Ok(message) => message,
+        Err(response) => return Ok(reply(&response, StatusCode::BAD_REQUEST)),
+    };
+
+    match message {
+        JSONRPCMessage::Single(request) => {
+            Ok(match serve_request(request, permission, &handlers).await {
+                Some(JSONRPCResponse::Error(response))
+                    if response.error.code == FORBIDDEN_ERROR_CODE =>
+                {
+                    reply(&response, StatusCode::FORBIDDEN)
+                }
+                Some(response) => reply(&response, StatusCode::OK),
+                None => StatusCode::NO_CONTENT.into_response(),
+            })
+        }
+        JSONRPCMessage::Batch(requests) => {
+            let responses = join_all(requests.into_iter().map(|request| {
+                let handlers = handlers.clone();
+                async move {
+                    match request {
+                        Ok(request) => serve_request(request, permission, &handlers).await,
+                        Err(response) => Some(JSONRPCResponse::Error(response)),
+                    }
+                }
+            }))
+            .await
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+            if responses.is_empty() {
+                Ok(StatusCode::NO_CONTENT.into_response())
+            } else {
+                Ok(reply(&responses, StatusCode::OK))
+            }
+        }
+    }
+}
+
+/// Serves `request` if `permission` allows to call its method. Returns its response, unless it
+/// is a notification.
+async fn serve_request(
+    request: JSONRPCRequest,
+    permission: Permission,
+    handlers: &Handlers,
+) -> Option<JSONRPCResponse> {
+    match authorize(&request, permission) {
+        Ok(()) => process_request(request, handlers).await,
+        Err(response) => response,
+    }
 }
 
-/// Main function responsible for handling and routing jsonrpc requests to the right underlying handler according to the method
-async fn handle_request(
+/// Checks that `permission` allows to call the method of `request`. Fails with the response to
+/// send otherwise, which is `None` for notifications.
+pub(crate) fn authorize(
+    request: &JSONRPCRequest,
+    permission: Permission,
+) -> Result<(), Option<JSONRPCResponse>> {
+    let required = required_permission(&request.method);
+    if permission >= required {
+        return Ok(());
+    }
+
+    log::debug!(
+        "method {:?} requires the {required} permission",
+        request.method
+    );
+    Err(request.id.clone().map(|id| {
+        JSONRPCResponse::Error(JSONRPCErrorResponse::forbidden(
+            id,
+            &request.method,
+            required,
+        ))
+    }))
+}
+
+/// Main function responsible for handling and routing jsonrpc requests to the right underlying
+/// handler according to the method, whatever the transport they were received from. Returns the
+/// response to the request, unless it is a notification.
+pub(crate) async fn process_request(
     json_rpc_request: JSONRPCRequest,
-    handlers: ArcHandlers,
-) -> Result<impl Reply, warp::Rejection> {
+    handlers: &Handlers,
+) -> Option<JSONRPCResponse> {
     log::debug!("received json rpc request = {:?}", json_rpc_request);
 
     let JSONRPCRequest {
-        id,
-        method,
-        params,
-        jsonrpc,
+        id, method, params, ..
     } = json_rpc_request;
 
-    if jsonrpc != JSON_RPC_VERSION {
-        return Ok(warp::reply::json(&JSONRPCErrorResponse::invalid_request(
-            id,
-        )));
-    }
-
     log::info!("received method = {method:?} and params = {params:?}");
-    match handlers.handle(method, params).await {
-        Ok(response) => Ok(warp::reply::json(&JSONRPCResultResponse::new(id, response))),
-        Err(e) => {
-            let error: JSONRPCError<()> = JSONRPCError {
-                code: -1,
-                data: None,
-                message: e.to_string(),
-            };
-            Ok(warp::reply::json(&JSONRPCErrorResponse::new(id, error)))
-        }
-    }
+    let result = handlers.handle(method, params).await;
+    id.map(|id| to_response(id, result))
 }
 
-/// The invalid parameter warp rejection error handling
-#[derive(Debug)]
-struct InvalidParameter;
+/// Returns the response to the request with `id` whose handling returned `result`.
+pub(crate) fn to_response(id: Id, result: Result<Value>) -> JSONRPCResponse {
+    match result {
+        Ok(result) => JSONRPCResponse::Result(JSONRPCResultResponse::new(id, result)),
+        Err(e) => JSONRPCResponse::Error(JSONRPCErrorResponse::new(
+            id,
+            JSONRPCError::from_handler_error(&e),
+        )),
+    }
+}
 
-impl Reject for InvalidParameter {}
+fn reply<T: Serialize>(body: &T, status: StatusCode) -> Response {
+    with_status(warp::reply::json(body), status).into_response()
+}
 
-async fn handle_rejection(err: Rejection) -> Result<impl Reply, warp::Rejection> {
+async fn handle_rejection(err: Rejection) -> Result<Response, warp::Rejection> {
     if err.is_not_found() {
-        Ok(with_status("NOT_FOUND", StatusCode::NOT_FOUND))
-    } else if err.find::<InvalidParameter>().is_some() {
-        Ok(with_status("BAD_REQUEST", StatusCode::BAD_REQUEST))
+        Ok(with_status("NOT_FOUND", StatusCode::NOT_FOUND).into_response())
+    } else if err.find::<Unauthorized>().is_some() {
+        // The request is rejected before its body is read, so its id is unknown.
+        let response = JSONRPCErrorResponse::<Value>::unauthorized(Id::Null);
+        Ok(reply(&response, StatusCode::UNAUTHORIZED))
     } else {
         log::error!("unhandled rejection: {:?}", err);
-        Ok(with_status(
-            "INTERNAL_SERVER_ERROR",
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+        Ok(with_status("INTERNAL_SERVER_ERROR", StatusCode::INTERNAL_SERVER_ERROR).into_response())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{JSON_RPC_ENDPOINT, JSON_RPC_VERSION};
-    use crate::server::jsonrpc::{json_rpc_filter, ArcHandlers, JSONRPCResultResponse};
-    use crate::server::request::JSONRPCRequest;
+    use crate::config::{
+        json_rpc_methods, Permission, ServerAuth, JSON_RPC_ENDPOINT, JSON_RPC_VERSION,
+    };
+    use crate::server::auth::AuthConfig;
+    use crate::server::jsonrpc::{
+        json_rpc_filter, ArcHandlers, JSONRPCErrorResponse, JSONRPCResultResponse,
+    };
+    use crate::server::openrpc::{OpenRpcDocument, OPENRPC_VERSION};
+    use crate::server::request::{Id, JSONRPCRequest};
+    use crate::server::response::{
+        FORBIDDEN_ERROR_CODE, INVALID_REQUEST_CODE, METHOD_NOT_FOUND_CODE, PARSE_ERROR_CODE,
+        UNAUTHORIZED_ERROR_CODE,
+    };
     use crate::server::Handlers;
+    use std::collections::HashMap;
     use std::sync::Arc;
     use warp::http::StatusCode;
 
+    const READ_TOKEN: &str = "READ_TOKEN";
+    const ADMIN_TOKEN: &str = "ADMIN_TOKEN";
+
     fn get_empty_handlers() -> ArcHandlers {
         Arc::new(Handlers::empty_handlers())
     }
 
+    fn no_auth() -> AuthConfig {
+        Arc::new(|| None)
+    }
+
+    fn token_auth() -> AuthConfig {
+        Arc::new(|| {
+            Some(ServerAuth {
+                tokens: HashMap::from([
+                    (String::from(READ_TOKEN), Permission::Read),
+                    (String::from(ADMIN_TOKEN), Permission::Admin),
+                ]),
+            })
+        })
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_json_rpc_filter_works() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
 
         let foo = "foo".to_string();
         let jsonrpc = String::from(JSON_RPC_VERSION);
-        let id = 0;
+        let id = Id::from(0);
 
         let req = JSONRPCRequest {
-            id,
+            id: Some(id.clone()),
             jsonrpc: jsonrpc.clone(),
             method: foo.clone(),
             params: Default::default(),
@@ -198,7 +374,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_json_rpc_filter_cannot_parse_param() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
 
         let value = warp::test::request()
             .method("POST")
@@ -210,9 +386,93 @@ mod tests {
         assert_eq!(StatusCode::BAD_REQUEST, value.status());
     }
 
+    #[tokio::test]
+    async fn test_json_rpc_filter_parse_error() {
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
+
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .body("{")
+            .reply(&filter)
+            .await;
+
+        assert_eq!(StatusCode::BAD_REQUEST, value.status());
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<()>>(value.body()).unwrap();
+        assert_eq!(v.id, Id::Null);
+        assert_eq!(v.error.code, PARSE_ERROR_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_filter_batch() {
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
+        let path = format!("/{JSON_RPC_ENDPOINT:}");
+
+        // The invalid requests and the requests with an id are answered, the notifications are not.
+        let batch = serde_json::json!([
+            { "jsonrpc": JSON_RPC_VERSION, "method": "foo", "id": "a" },
+            { "jsonrpc": JSON_RPC_VERSION, "method": "foo" },
+            { "jsonrpc": JSON_RPC_VERSION, "method": "foo", "id": null },
+            { "foo": "bar" },
+        ]);
+        let value = warp::test::request()
+            .method("POST")
+            .path(&path)
+            .json(&batch)
+            .reply(&filter)
+            .await;
+        assert_eq!(StatusCode::OK, value.status());
+
+        let responses =
+            serde_json::from_slice::<Vec<JSONRPCErrorResponse<()>>>(value.body()).unwrap();
+        let codes = responses
+            .into_iter()
+            .map(|r| (r.id, r.error.code))
+            .collect::<Vec<_>>();
+        assert_eq!(codes.len(), 3);
+        assert!(codes.contains(&(Id::from("a"), METHOD_NOT_FOUND_CODE)));
+        assert!(codes.contains(&(Id::Null, METHOD_NOT_FOUND_CODE)));
+        assert!(codes.contains(&(Id::Null, INVALID_REQUEST_CODE)));
+
+        // Nothing is replied to notifications.
+        let batch = serde_json::json!([{ "jsonrpc": JSON_RPC_VERSION, "method": "foo" }]);
+        for body in [batch.clone(), batch[0].clone()] {
+            let value = warp::test::request()
+                .method("POST")
+                .path(&path)
+                .json(&body)
+                .reply(&filter)
+                .await;
+            assert_eq!(StatusCode::NO_CONTENT, value.status());
+            assert!(value.body().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_filter_discover() {
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
+
+        let value = warp::test::request()
+            .method("POST")
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .json(&serde_json::json!({
+                "jsonrpc": JSON_RPC_VERSION,
+                "method": json_rpc_methods::RPC_DISCOVER,
+                "id": 1,
+            }))
+            .reply(&filter)
+            .await;
+
+        let v =
+            serde_json::from_slice::<JSONRPCResultResponse<OpenRpcDocument>>(value.body()).unwrap();
+        assert_eq!(v.result.openrpc, OPENRPC_VERSION);
+        // `rpc.discover` and the subscription methods.
+        assert_eq!(v.result.methods.len(), 4);
+    }
+
     #[tokio::test]
     async fn test_json_rpc_filter_not_found() {
-        let filter = json_rpc_filter(get_empty_handlers());
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
 
         let value = warp::test::request()
             .method("POST")
@@ -223,4 +483,141 @@ mod tests {
 
         assert_eq!(StatusCode::NOT_FOUND, value.status());
     }
+
+    #[tokio::test]
+    async fn test_json_rpc_filter_auth() {
+        let filter = json_rpc_filter(get_empty_handlers(), token_auth());
+
+        let req = |method: &str| JSONRPCRequest {
+            id: Some(Id::from(1)),
+            jsonrpc: String::from(JSON_RPC_VERSION),
+            method: String::from(method),
+            params: serde_json::Value::Null,
+        };
+        let path = format!("/{JSON_RPC_ENDPOINT:}");
+
+        // The requests without a known token are rejected.
+        for authorization in [None, Some("Bearer UNKNOWN"), Some(READ_TOKEN)] {
+            let mut request = warp::test::request().method("POST").path(&path);
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let value = request
+                .json(&req(json_rpc_methods::LIST_CHILD_SUBNETS))
+                .reply(&filter)
+                .await;
+            assert_eq!(StatusCode::UNAUTHORIZED, value.status());
+            let v = serde_json::from_slice::<JSONRPCErrorResponse<()>>(value.body()).unwrap();
+            assert_eq!(v.error.code, UNAUTHORIZED_ERROR_CODE);
+        }
+
+        // The tokens only allow the methods within their permission.
+        let value = warp::test::request()
+            .method("POST")
+            .path(&path)
+            .header("authorization", format!("Bearer {READ_TOKEN}"))
+            .json(&req(json_rpc_methods::CREATE_SUBNET))
+            .reply(&filter)
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, value.status());
+        let v = serde_json::from_slice::<JSONRPCErrorResponse<()>>(value.body()).unwrap();
+        assert_eq!(v.id, Id::from(1));
+        assert_eq!(v.error.code, FORBIDDEN_ERROR_CODE);
+
+        for (token, method) in [
+            (READ_TOKEN, json_rpc_methods::LIST_CHILD_SUBNETS),
+            (ADMIN_TOKEN, json_rpc_methods::CREATE_SUBNET),
+        ] {
+            let value = warp::test::request()
+                .method("POST")
+                .path(&path)
+                .header("authorization", format!("Bearer {token}"))
+                .json(&req(method))
+                .reply(&filter)
+                .await;
+            assert_eq!(StatusCode::OK, value.status());
+            let v = serde_json::from_slice::<JSONRPCErrorResponse<()>>(value.body()).unwrap();
+            assert_eq!(v.error.code, METHOD_NOT_FOUND_CODE);
+        }
+
+        // The websocket connections are authenticated on upgrade.
+        let rejected = warp::test::ws().path(&path).handshake(filter.clone()).await;
+        assert!(rejected.is_err());
+
+        let mut client = warp::test::ws()
+            .path(&path)
+            .header("authorization", format!("Bearer {READ_TOKEN}"))
+            .handshake(filter)
+            .await
+            .unwrap();
+        for method in [
+            json_rpc_methods::RELOAD_CONFIG,
+            json_rpc_methods::SUBSCRIBE_CHECKPOINTS,
+        ] {
+            client
+                .send_text(serde_json::to_string(&req(method)).unwrap())
+                .await;
+            let message = client.recv().await.unwrap();
+            let v = serde_json::from_str::<JSONRPCErrorResponse<()>>(message.to_str().unwrap())
+                .unwrap();
+            assert_eq!(v.error.code, FORBIDDEN_ERROR_CODE);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_ws() {
+        let filter = json_rpc_filter(get_empty_handlers(), no_auth());
+        let mut client = warp::test::ws()
+            .path(&format!("/{JSON_RPC_ENDPOINT:}"))
+            .handshake(filter)
+            .await
+            .unwrap();
+
+        let request = |id: u64, method: &str, params: serde_json::Value| {
+            let req = JSONRPCRequest {
+                id: Some(Id::from(id)),
+                jsonrpc: String::from(JSON_RPC_VERSION),
+                method: String::from(method),
+                params,
+            };
+            serde_json::to_string(&req).unwrap()
+        };
+
+        // The requests are routed to the handlers.
+        client
+            .send_text(request(1, "foo", serde_json::Value::Null))
+            .await;
+        let message = client.recv().await.unwrap();
+        let v =
+            serde_json::from_str::<JSONRPCErrorResponse<()>>(message.to_str().unwrap()).unwrap();
+        assert_eq!(v.id, Id::from(1));
+        assert_eq!(v.error.code, METHOD_NOT_FOUND_CODE);
+
+        // The subscriptions are served by the websocket transport.
+        client
+            .send_text(request(
+                2,
+                json_rpc_methods::SUBSCRIBE_CHECKPOINTS,
+                serde_json::Value::Null,
+            ))
+            .await;
+        let message = client.recv().await.unwrap();
+        let v =
+            serde_json::from_str::<JSONRPCResultResponse<u64>>(message.to_str().unwrap()).unwrap();
+        assert_eq!(v.id, Id::from(2));
+        let subscription = v.result;
+
+        client
+            .send_text(request(
+                3,
+                json_rpc_methods::UNSUBSCRIBE,
+                serde_json::json!({ "subscription": subscription }),
+            ))
+            .await;
+        let message = client.recv().await.unwrap();
+        let v =
+            serde_json::from_str::<JSONRPCResultResponse<bool>>(message.to_str().unwrap()).unwrap();
+        assert_eq!(v.id, Id::from(3));
+        assert!(v.result);
+    }
 }
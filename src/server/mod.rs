@@ -4,22 +4,30 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
 
+mod auth;
+mod error;
 mod handlers;
 pub mod jsonrpc;
+pub mod openrpc;
 pub mod request;
 pub mod response;
+mod ws;
 
+pub(crate) use auth::required_permission;
+pub use error::RequestError;
 pub use handlers::*;
 
-/// The JSON RPC server request handler trait.
+/// The JSON RPC server request handler trait. The JSON schemas of its request and response are
+/// published in the OpenRPC document of the server.
 #[async_trait]
 pub trait JsonRPCRequestHandler {
-    type Request: Debug + DeserializeOwned + Send;
-    type Response: Debug + Serialize;
+    type Request: Debug + DeserializeOwned + Send + JsonSchema;
+    type Response: Debug + Serialize + JsonSchema;
 
     /// Handles the request sent to the json rpc server. Returns a response back.
     async fn handle(&self, request: Self::Request) -> Result<Self::Response>;
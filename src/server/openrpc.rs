@@ -0,0 +1,235 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The OpenRPC document describing the methods of the json rpc server, with the JSON schemas of
+//! their params and results. See the spec: https://spec.open-rpc.org
+
+use crate::config::json_rpc_methods;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{Schema, SchemaObject};
+use serde::{Deserialize, Serialize};
+
+/// The version of the OpenRPC spec the document follows.
+pub const OPENRPC_VERSION: &str = "1.2.6";
+/// The path of the schemas shared by the methods in the document.
+const SCHEMAS_PATH: &str = "#/components/schemas/";
+/// The schema of the OpenRPC documents, which are the results of `rpc.discover`.
+const OPENRPC_META_SCHEMA: &str =
+    "https://raw.githubusercontent.com/open-rpc/meta-schema/master/schema.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRpcDocument {
+    pub openrpc: String,
+    pub info: Info,
+    pub methods: Vec<MethodObject>,
+    pub components: Components,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodObject {
+    pub name: String,
+    pub params: Vec<ContentDescriptor>,
+    pub result: ContentDescriptor,
+    /// Either `by-name` or `by-position`. The params of the methods of the agent are passed as
+    /// an object.
+    pub param_structure: String,
+    /// The result of the notifications of a subscription method, which are sent through
+    /// `ipc_subscription`. It is an extension of the spec.
+    #[serde(
+        rename = "x-notification",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notification: Option<ContentDescriptor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentDescriptor {
+    pub name: String,
+    pub required: bool,
+    pub schema: Schema,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Components {
+    pub schemas: schemars::Map<String, Schema>,
+}
+
+/// Builds an [`OpenRpcDocument`] out of the methods and the schemas of their params and results,
+/// which are generated by the generator of the builder so that they share their definitions.
+pub(crate) struct OpenRpcBuilder {
+    generator: SchemaGenerator,
+    methods: Vec<MethodObject>,
+}
+
+impl Default for OpenRpcBuilder {
+    fn default() -> Self {
+        let settings = SchemaSettings::draft07().with(|s| {
+            s.definitions_path = String::from(SCHEMAS_PATH);
+            s.meta_schema = None;
+        });
+        Self {
+            generator: settings.into_generator(),
+            methods: vec![],
+        }
+    }
+}
+
+impl OpenRpcBuilder {
+    pub fn generator(&mut self) -> &mut SchemaGenerator {
+        &mut self.generator
+    }
+
+    /// Adds the method `name`. Each property of the `params` schema is listed as a param of the
+    /// method, unless the schema is not the one of an object.
+    pub fn add_method(&mut self, name: &str, params: Schema, result: Schema) {
+        let params = match self
+            .generator
+            .dereference(&params)
+            .cloned()
+            .unwrap_or(params)
+        {
+            Schema::Object(SchemaObject {
+                object: Some(object),
+                ..
+            }) => object
+                .properties
+                .iter()
+                .map(|(property, schema)| ContentDescriptor {
+                    name: property.clone(),
+                    required: object.required.contains(property),
+                    schema: schema.clone(),
+                })
+                .collect(),
+            schema => vec![ContentDescriptor {
+                name: String::from("params"),
+                required: true,
+                schema,
+            }],
+        };
+
+        self.methods.push(MethodObject {
+            name: String::from(name),
+            params,
+            result: ContentDescriptor {
+                name: format!("{name}Result"),
+                required: true,
+                schema: result,
+            },
+            param_structure: String::from("by-name"),
+            notification: None,
+        });
+    }
+
+    /// Adds the subscription method `name`, whose result is the id of the subscription. The
+    /// results of its notifications follow the `notification` schema.
+    pub fn add_subscription(&mut self, name: &str, params: Schema, notification: Schema) {
+        let result = self.generator.subschema_for::<u64>();
+        self.add_method(name, params, result);
+        let method = self.methods.last_mut().expect("method just added");
+        method.notification = Some(ContentDescriptor {
+            name: format!("{name}Notification"),
+            required: true,
+            schema: notification,
+        });
+    }
+
+    /// Returns the document with the methods sorted by name, along with `rpc.discover`.
+    pub fn build(mut self) -> OpenRpcDocument {
+        self.methods.push(MethodObject {
+            name: String::from(json_rpc_methods::RPC_DISCOVER),
+            params: vec![],
+            result: ContentDescriptor {
+                name: String::from("OpenRPC Schema"),
+                required: true,
+                schema: Schema::new_ref(String::from(OPENRPC_META_SCHEMA)),
+            },
+            param_structure: String::from("by-name"),
+            notification: None,
+        });
+        self.methods.sort_by(|a, b| a.name.cmp(&b.name));
+
+        OpenRpcDocument {
+            openrpc: String::from(OPENRPC_VERSION),
+            info: Info {
+                title: String::from("IPC Agent JSON-RPC API"),
+                version: String::from(env!("CARGO_PKG_VERSION")),
+            },
+            methods: self.methods,
+            components: Components {
+                schemas: self.generator.take_definitions(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::json_rpc_methods;
+    use crate::server::openrpc::OpenRpcBuilder;
+    use schemars::JsonSchema;
+    use serde_json::json;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Params {
+        subnet: String,
+        from: Option<String>,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Response {
+        receipt: Receipt,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Receipt {
+        exit_code: u32,
+    }
+
+    #[test]
+    fn build_document() {
+        let mut builder = OpenRpcBuilder::default();
+        let params = builder.generator().subschema_for::<Params>();
+        let result = builder.generator().subschema_for::<Response>();
+        builder.add_method("foo", params, result);
+        let unit = builder.generator().subschema_for::<()>();
+        builder.add_method("bar", unit.clone(), unit);
+
+        let document = serde_json::to_value(builder.build()).unwrap();
+        let methods = document["methods"].as_array().unwrap();
+        let names = methods
+            .iter()
+            .map(|m| m["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["bar", "foo", json_rpc_methods::RPC_DISCOVER]);
+
+        // The fields of the params are listed as params.
+        let foo = &methods[1];
+        assert_eq!(foo["paramStructure"], "by-name");
+        assert_eq!(foo["params"][0]["name"], "from");
+        assert_eq!(foo["params"][0]["required"], false);
+        assert_eq!(foo["params"][1]["name"], "subnet");
+        assert_eq!(foo["params"][1]["required"], true);
+
+        // The schemas refer to the shared ones in the components.
+        assert_eq!(
+            foo["result"]["schema"],
+            json!({ "$ref": "#/components/schemas/Response" })
+        );
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["Response"]["properties"]["receipt"],
+            json!({ "$ref": "#/components/schemas/Receipt" })
+        );
+        assert!(schemas["Receipt"].is_object());
+    }
+}
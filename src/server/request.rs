@@ -1,14 +1,156 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use serde::{Deserialize, Serialize};
+use crate::config::JSON_RPC_VERSION;
+use crate::server::response::JSONRPCErrorResponse;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+/// The id of a json rpc request, echoed in its response. As per the spec, it is either a string,
+/// a number or null: https://www.jsonrpc.org/specification#request_object
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Id {
+    Number(serde_json::Number),
+    String(String),
+    Null,
+}
+
+impl From<u64> for Id {
+    fn from(id: u64) -> Self {
+        Id::Number(id.into())
+    }
+}
+
+impl From<&str> for Id {
+    fn from(id: &str) -> Self {
+        Id::String(String::from(id))
+    }
+}
+
 /// The json rpc request param. It is the standard form our json-rpc and follows a structure similar
 /// to the one of the Ethereum RPC: https://ethereum.org/en/developers/docs/apis/json-rpc/#curl-examples
 #[derive(Serialize, Deserialize, Debug)]
 pub struct JSONRPCRequest {
-    pub id: u16,
+    /// The id of the request, missing if it is a notification, to which no response is sent.
+    /// A null id is kept apart from a missing one.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Id>,
     pub jsonrpc: String,
     pub method: String,
+    #[serde(default)]
     pub params: Value,
 }
+
+impl JSONRPCRequest {
+    /// Whether no response is expected for the request.
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
+
+/// The requests of a json rpc message, which is either a single request or a batch of them.
+#[derive(Debug)]
+pub enum JSONRPCMessage {
+    Single(JSONRPCRequest),
+    /// The requests of a batch, each of them either valid or failed with the response to send.
+    Batch(Vec<Result<JSONRPCRequest, JSONRPCErrorResponse<Value>>>),
+}
+
+impl JSONRPCMessage {
+    /// Parses the message in `bytes`. Fails with the response to send if it is not valid json,
+    /// or is neither a valid request nor a non-empty batch.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, JSONRPCErrorResponse<Value>> {
+        let value = serde_json::from_slice::<Value>(bytes).map_err(|e| {
+            log::debug!("cannot parse {bytes:?} due to {e:?}");
+            JSONRPCErrorResponse::parse_error()
+        })?;
+
+        match value {
+            Value::Array(values) if values.is_empty() => {
+                Err(JSONRPCErrorResponse::invalid_request(Id::Null))
+            }
+            Value::Array(values) => Ok(JSONRPCMessage::Batch(
+                values.into_iter().map(to_request).collect(),
+            )),
+            value => to_request(value).map(JSONRPCMessage::Single),
+        }
+    }
+}
+
+/// Converts `value` into a request, or into the invalid request response to send.
+fn to_request(value: Value) -> Result<JSONRPCRequest, JSONRPCErrorResponse<Value>> {
+    let request = serde_json::from_value::<JSONRPCRequest>(value).map_err(|e| {
+        log::debug!("cannot deserialize request due to {e:?}");
+        JSONRPCErrorResponse::invalid_request(Id::Null)
+    })?;
+
+    if request.jsonrpc != JSON_RPC_VERSION {
+        return Err(JSONRPCErrorResponse::invalid_request(
+            request.id.unwrap_or(Id::Null),
+        ));
+    }
+    Ok(request)
+}
+
+fn deserialize_id<'de, D>(deserializer: D) -> Result<Option<Id>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Id::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::request::{Id, JSONRPCMessage};
+
+    fn parse(s: &str) -> JSONRPCMessage {
+        JSONRPCMessage::from_slice(s.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn parse_ids() {
+        let id = |s: &str| match parse(s) {
+            JSONRPCMessage::Single(request) => request.id,
+            JSONRPCMessage::Batch(_) => panic!("not a single request"),
+        };
+
+        assert_eq!(
+            id(r#"{"jsonrpc":"2.0","method":"foo","id":1}"#),
+            Some(Id::from(1))
+        );
+        assert_eq!(
+            id(r#"{"jsonrpc":"2.0","method":"foo","id":"abc"}"#),
+            Some(Id::from("abc"))
+        );
+        assert_eq!(
+            id(r#"{"jsonrpc":"2.0","method":"foo","id":null}"#),
+            Some(Id::Null)
+        );
+        assert_eq!(id(r#"{"jsonrpc":"2.0","method":"foo"}"#), None);
+    }
+
+    #[test]
+    fn parse_batches() {
+        let requests = match parse(
+            r#"[{"jsonrpc":"2.0","method":"foo","id":1},{"jsonrpc":"1.0","method":"foo","id":2},3]"#,
+        ) {
+            JSONRPCMessage::Batch(requests) => requests,
+            JSONRPCMessage::Single(_) => panic!("not a batch"),
+        };
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].is_ok());
+        assert_eq!(requests[1].as_ref().unwrap_err().id, Id::from(2));
+        assert_eq!(requests[2].as_ref().unwrap_err().id, Id::Null);
+    }
+
+    #[test]
+    fn reject_invalid_messages() {
+        for s in ["{", "[]", "3", r#"{"jsonrpc":"2.0","id":1}"#] {
+            assert!(JSONRPCMessage::from_slice(s.as_bytes()).is_err());
+        }
+    }
+}
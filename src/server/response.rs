@@ -1,22 +1,38 @@
 // Copyright 2022-2023 Protocol Labs
 // SPDX-License-Identifier: MIT
-use crate::config::JSON_RPC_VERSION;
+use crate::config::{Permission, JSON_RPC_VERSION};
+use crate::manager::MessageExecutionError;
+use crate::server::request::Id;
+use crate::server::RequestError;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// List of error codes for json rpc, see more: https://www.jsonrpc.org/specification#error_object
-const INVALID_REQUEST_CODE: i32 = -32600;
+pub const PARSE_ERROR_CODE: i32 = -32700;
+pub const INVALID_REQUEST_CODE: i32 = -32600;
+pub const METHOD_NOT_FOUND_CODE: i32 = -32601;
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+/// The code of the errors of the handlers that have no specific code.
+pub const INTERNAL_ERROR_CODE: i32 = -32603;
+/// The code of the errors of messages whose execution failed, in the range reserved for
+/// implementation-defined server errors.
+pub const MESSAGE_EXECUTION_ERROR_CODE: i32 = -32000;
+/// The code of the errors of requests without a valid bearer token.
+pub const UNAUTHORIZED_ERROR_CODE: i32 = -32001;
+/// The code of the errors of requests whose token does not grant the permission of their method.
+pub const FORBIDDEN_ERROR_CODE: i32 = -32003;
 
 /// The json rpc result response. It is the standard form our json-rpc and follows
 /// the spec: https://www.jsonrpc.org/specification#response_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JSONRPCResultResponse<T> {
-    pub id: u16,
+    pub id: Id,
     pub jsonrpc: String,
     pub result: T,
 }
 
 impl<T: Serialize> JSONRPCResultResponse<T> {
-    pub fn new(id: u16, result: T) -> Self {
+    pub fn new(id: Id, result: T) -> Self {
         Self {
             id,
             jsonrpc: String::from(JSON_RPC_VERSION),
@@ -33,16 +49,64 @@ pub struct JSONRPCError<T> {
     pub data: Option<T>,
 }
 
+impl JSONRPCError<Value> {
+    /// Returns the error object of a request whose handler failed with `error`. If a message
+    /// failed to execute, the `MessageExecutionError` is returned as the data of the error. The
+    /// errors without a specific code are internal errors.
+    pub fn from_handler_error(error: &anyhow::Error) -> Self {
+        if let Some(e) = error.downcast_ref::<RequestError>() {
+            return match e {
+                RequestError::MethodNotFound(method) => Self {
+                    code: METHOD_NOT_FOUND_CODE,
+                    message: String::from("Method not found"),
+                    data: Some(Value::String(method.clone())),
+                },
+                RequestError::InvalidParams(e) => Self {
+                    code: INVALID_PARAMS_CODE,
+                    message: String::from("Invalid params"),
+                    data: Some(Value::String(e.to_string())),
+                },
+            };
+        }
+
+        match error.downcast_ref::<MessageExecutionError>() {
+            Some(e) => Self {
+                code: MESSAGE_EXECUTION_ERROR_CODE,
+                message: error.to_string(),
+                data: serde_json::to_value(e).ok(),
+            },
+            None => Self {
+                code: INTERNAL_ERROR_CODE,
+                message: error.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
 /// The json rpc error response. It is the standard form our json-rpc and follows the spec: https://www.jsonrpc.org/specification#response_object
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JSONRPCErrorResponse<T> {
-    pub id: u16,
+    pub id: Id,
     pub jsonrpc: String,
     pub error: JSONRPCError<T>,
 }
 
-impl JSONRPCErrorResponse<()> {
-    pub fn invalid_request(id: u16) -> Self {
+impl<T> JSONRPCErrorResponse<T> {
+    /// The response to a message that is not valid json, whose id is unknown.
+    pub fn parse_error() -> Self {
+        Self {
+            id: Id::Null,
+            jsonrpc: String::from(JSON_RPC_VERSION),
+            error: JSONRPCError {
+                code: PARSE_ERROR_CODE,
+                message: String::from("Parse error"),
+                data: None,
+            },
+        }
+    }
+
+    pub fn invalid_request(id: Id) -> Self {
         Self {
             id,
             jsonrpc: String::from(JSON_RPC_VERSION),
@@ -53,9 +117,34 @@ impl JSONRPCErrorResponse<()> {
             },
         }
     }
+
+    pub fn unauthorized(id: Id) -> Self {
+        Self {
+            id,
+            jsonrpc: String::from(JSON_RPC_VERSION),
+            error: JSONRPCError {
+                code: UNAUTHORIZED_ERROR_CODE,
+                message: String::from("Unauthorized"),
+                data: None,
+            },
+        }
+    }
+
+    pub fn forbidden(id: Id, method: &str, required: Permission) -> Self {
+        Self {
+            id,
+            jsonrpc: String::from(JSON_RPC_VERSION),
+            error: JSONRPCError {
+                code: FORBIDDEN_ERROR_CODE,
+                message: format!("Forbidden: {method} requires the {required} permission"),
+                data: None,
+            },
+        }
+    }
 }
+
 impl<T: Serialize> JSONRPCErrorResponse<T> {
-    pub fn new(id: u16, error: JSONRPCError<T>) -> Self {
+    pub fn new(id: Id, error: JSONRPCError<T>) -> Self {
         Self {
             id,
             jsonrpc: String::from(JSON_RPC_VERSION),
@@ -63,3 +152,11 @@ impl<T: Serialize> JSONRPCErrorResponse<T> {
         }
     }
 }
+
+/// Either of the json rpc responses, as returned for a request by the handlers.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JSONRPCResponse {
+    Result(JSONRPCResultResponse<Value>),
+    Error(JSONRPCErrorResponse<Value>),
+}
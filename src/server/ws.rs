@@ -0,0 +1,206 @@
+// Copyright 2022-2023 Protocol Labs
+// SPDX-License-Identifier: MIT
+//! The websocket transport of the json rpc server. Besides the requests served by the handlers,
+//! it serves the subscriptions, whose notifications are pushed to the client as they happen.
+
+use crate::config::{json_rpc_methods, Permission, JSON_RPC_VERSION};
+use crate::server::jsonrpc::{authorize, process_request, to_response};
+use crate::server::request::{JSONRPCMessage, JSONRPCRequest};
+use crate::server::response::JSONRPCResponse;
+use crate::server::{
+    Handlers, RequestError, Subscription, SubscriptionNotification, UnsubscribeParams,
+};
+use futures_util::future::{join_all, ready, BoxFuture};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use warp::ws::{Message, WebSocket};
+
+/// Serves the json rpc requests received through `socket` until it is closed. The requests are
+/// processed concurrently, so their responses may be sent in a different order. Only the methods
+/// allowed by `permission`, granted to the token of the connection, are served.
+pub(crate) async fn serve_ws(socket: WebSocket, handlers: Arc<Handlers>, permission: Permission) {
+    let (mut sink, mut stream) = socket.split();
+
+    // The responses and notifications are sent from different tasks, so they are all funneled
+    // through a channel to the single writer of the socket.
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        while let Some(text) = outgoing_rx.recv().await {
+            if let Err(e) = sink.send(Message::text(text)).await {
+                log::debug!("cannot write to websocket: {e:?}");
+                break;
+            }
+        }
+    });
+
+    let mut connection = Connection {
+        handlers,
+        permission,
+        outgoing,
+        subscriptions: HashMap::new(),
+        next_subscription_id: 1,
+    };
+    while let Some(message) = stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                log::debug!("cannot read from websocket: {e:?}");
+                break;
+            }
+        };
+        if message.is_close() {
+            break;
+        }
+        // Pings are answered by warp, and binary messages are not supported.
+        if let Ok(text) = message.to_str() {
+            connection.handle_message(text);
+        }
+    }
+
+    for (_, task) in connection.subscriptions.drain() {
+        task.abort();
+    }
+    writer.abort();
+}
+
+/// The state of a websocket connection.
+struct Connection {
+    handlers: Arc<Handlers>,
+    permission: Permission,
+    outgoing: mpsc::UnboundedSender<String>,
+    /// The tasks forwarding the notifications of each subscription, by subscription id.
+    subscriptions: HashMap<u64, JoinHandle<()>>,
+    next_subscription_id: u64,
+}
+
+impl Connection {
+    /// Handles a json rpc message, which is either a single request or a batch of them. The
+    /// responses of a batch are sent together once all of its requests are handled.
+    fn handle_message(&mut self, text: &str) {
+        let message = match JSONRPCMessage::from_slice(text.as_bytes()) {
+            Ok(message) => message,
+            Err(response) => {
+                self.send(&response);
+                return;
+            }
+        };
+
+        let outgoing = self.outgoing.clone();
+        match message {
+            JSONRPCMessage::Single(request) => {
+                let response = self.handle_request(request);
+                tokio::spawn(async move {
+                    if let Some(response) = response.await {
+                        send(&outgoing, &response);
+                    }
+                });
+            }
+            JSONRPCMessage::Batch(requests) => {
+                let responses = requests
+                    .into_iter()
+                    .map(|request| match request {
+                        Ok(request) => self.handle_request(request),
+                        Err(response) => ready(Some(JSONRPCResponse::Error(response))).boxed(),
+                    })
+                    .collect::<Vec<_>>();
+                tokio::spawn(async move {
+                    let responses = join_all(responses)
+                        .await
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>();
+                    if !responses.is_empty() {
+                        send(&outgoing, &responses);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Handles `request`. The subscriptions are served right away, while the other requests are
+    /// processed by the handlers once the returned future is polled. It resolves to the response
+    /// of the request, unless it is a notification.
+    fn handle_request(
+        &mut self,
+        request: JSONRPCRequest,
+    ) -> BoxFuture<'static, Option<JSONRPCResponse>> {
+        if let Err(response) = authorize(&request, self.permission) {
+            return ready(response).boxed();
+        }
+
+        if request.method == json_rpc_methods::UNSUBSCRIBE {
+            let result = self.unsubscribe(request.params);
+            return ready(request.id.map(|id| to_response(id, result))).boxed();
+        }
+
+        match self
+            .handlers
+            .subscribe(&request.method, request.params.clone())
+        {
+            Some(subscription) => {
+                let result = subscription.map(|s| json!(self.start_subscription(s)));
+                ready(request.id.map(|id| to_response(id, result))).boxed()
+            }
+            None => {
+                let handlers = self.handlers.clone();
+                async move { process_request(request, &handlers).await }.boxed()
+            }
+        }
+    }
+
+    /// Forwards the notifications of `subscription` to the client until it is cancelled. Returns
+    /// the id of the subscription.
+    fn start_subscription(&mut self, mut subscription: Subscription) -> u64 {
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+
+        let outgoing = self.outgoing.clone();
+        let task = tokio::spawn(async move {
+            while let Some(result) = subscription.recv().await {
+                let notification = json!({
+                    "jsonrpc": JSON_RPC_VERSION,
+                    "method": json_rpc_methods::SUBSCRIPTION,
+                    "params": SubscriptionNotification {
+                        subscription: id,
+                        result,
+                    },
+                });
+                if outgoing.send(notification.to_string()).is_err() {
+                    break;
+                }
+            }
+        });
+        self.subscriptions.insert(id, task);
+
+        id
+    }
+
+    /// Cancels the subscription in `params`. Returns whether the subscription existed.
+    fn unsubscribe(&mut self, params: Value) -> anyhow::Result<Value> {
+        let params = serde_json::from_value::<UnsubscribeParams>(params)
+            .map_err(RequestError::InvalidParams)?;
+        let task = self.subscriptions.remove(&params.subscription);
+        if let Some(task) = &task {
+            task.abort();
+        }
+        Ok(json!(task.is_some()))
+    }
+
+    fn send<T: serde::Serialize>(&self, response: &T) {
+        send(&self.outgoing, response)
+    }
+}
+
+fn send<T: serde::Serialize>(outgoing: &mpsc::UnboundedSender<String>, response: &T) {
+    match serde_json::to_string(response) {
+        // The writer is gone only when the connection is closing.
+        Ok(text) => {
+            let _ = outgoing.send(text);
+        }
+        Err(e) => log::error!("cannot serialize json rpc response: {e:?}"),
+    }
+}